@@ -0,0 +1,83 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{DeriveInput, Error, Expr, Ident, Path, Token, Type};
+
+/// 没有指定`mailbox`时的信箱大小
+const DEFAULT_MAILBOX: u32 = 100;
+
+/// `#[actor(...)]`中的一项
+enum ActorArg {
+    Mailbox(Expr),
+    Restarts(Expr),
+    Args(Type),
+    Create(Path),
+}
+
+impl Parse for ActorArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse::<Ident>()?;
+        input.parse::<Token![=]>()?;
+        Ok(match key.to_string().as_str() {
+            "mailbox" => ActorArg::Mailbox(input.parse()?),
+            "restarts" => ActorArg::Restarts(input.parse()?),
+            "args" => ActorArg::Args(input.parse()?),
+            "create" => ActorArg::Create(input.parse()?),
+            _ => {
+                return Err(Error::new_spanned(
+                    key,
+                    "expected `mailbox`, `restarts`, `args` or `create`",
+                ))
+            }
+        })
+    }
+}
+
+pub fn derive(derive: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &derive.ident;
+    let mut mailbox = None;
+    let mut restarts = None;
+    let mut args = None;
+    let mut create = None;
+    for attr in derive.attrs.iter().filter(|attr| attr.path.is_ident("actor")) {
+        let items = attr.parse_args_with(Punctuated::<ActorArg, Token![,]>::parse_terminated)?;
+        for item in items {
+            match item {
+                ActorArg::Mailbox(expr) => mailbox = Some(expr),
+                ActorArg::Restarts(expr) => restarts = Some(expr),
+                ActorArg::Args(ty) => args = Some(ty),
+                ActorArg::Create(path) => create = Some(path),
+            }
+        }
+    }
+
+    let mailbox = mailbox.map_or_else(|| quote!(#DEFAULT_MAILBOX), |expr| quote!(#expr));
+    let restarts = restarts.map(|expr| quote!(const MAX_RESTARTS: u16 = #expr;));
+    // 指定了参数时通过`From`创建, 否则通过`Default`
+    let (args, body) = match (args, create) {
+        (args, Some(create)) => (
+            args.map_or_else(|| quote!(()), |ty| quote!(#ty)),
+            quote!(#create(ctx).await),
+        ),
+        (Some(ty), None) => (
+            quote!(#ty),
+            quote!(::std::convert::From::from(::std::clone::Clone::clone(&ctx.create_args))),
+        ),
+        (None, None) => (quote!(()), quote!(::std::default::Default::default())),
+    };
+    let (impl_generics, ty_generics, where_clause) = derive.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ractor::Actor for #name #ty_generics #where_clause {
+            const MAIL_BOX_SIZE: u32 = #mailbox;
+            #restarts
+            type Args = #args;
+
+            #[allow(unused_variables)]
+            async fn create(ctx: &mut ractor::Context<Self>) -> Self {
+                #body
+            }
+        }
+    })
+}
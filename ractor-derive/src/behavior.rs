@@ -0,0 +1,130 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Data, DeriveInput, Error, Expr, Fields, Ident, ItemImpl, Pat, Token};
+
+pub fn derive(derive: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &derive.ident;
+    let variants = match &derive.data {
+        Data::Enum(data) if !data.variants.is_empty() => &data.variants,
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "`Behavior` can only be derived for non-empty enums",
+            ))
+        }
+    };
+    if let Some(variant) = variants.iter().find(|v| !matches!(v.fields, Fields::Unit)) {
+        return Err(Error::new_spanned(
+            variant,
+            "`Behavior` variants cannot have fields",
+        ));
+    }
+
+    let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+    let names = idents.iter().map(|ident| ident.to_string());
+    let indexes = (0..idents.len()).collect::<Vec<_>>();
+    let (impl_generics, ty_generics, where_clause) = derive.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ractor::Behavior for #name #ty_generics #where_clause {
+            #[inline]
+            fn index(self) -> usize {
+                match self {
+                    #(#name::#idents => #indexes),*
+                }
+            }
+
+            #[inline]
+            fn from_index(index: usize) -> Self {
+                match index {
+                    #(#indexes => #name::#idents,)*
+                    _ => unreachable!("invalid behavior index: {}", index),
+                }
+            }
+
+            #[inline]
+            fn name(self) -> &'static str {
+                match self {
+                    #(#name::#idents => #names),*
+                }
+            }
+        }
+    })
+}
+
+pub struct BehaviorArgs {
+    pats: Punctuated<Pat, Token![|]>,
+    unhandled: Option<Unhandled>,
+}
+
+enum Unhandled {
+    Stash,
+    DeadLetter,
+    Reply(Box<Expr>),
+}
+
+impl Parse for BehaviorArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let pats = Punctuated::parse_separated_nonempty(input)?;
+        let mut unhandled = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            if key != "unhandled" {
+                return Err(Error::new_spanned(key, "expected `unhandled = ...`"));
+            }
+            input.parse::<Token![=]>()?;
+            let kind = input.parse::<Ident>()?;
+            unhandled = Some(match kind.to_string().as_str() {
+                "stash" => Unhandled::Stash,
+                "dead_letter" => Unhandled::DeadLetter,
+                "reply" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    Unhandled::Reply(Box::new(content.parse()?))
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        kind,
+                        "expected `stash`, `dead_letter` or `reply(...)`",
+                    ))
+                }
+            });
+        }
+        Ok(BehaviorArgs { pats, unhandled })
+    }
+}
+
+pub fn expand(args: BehaviorArgs, mut item: ItemImpl) -> syn::Result<TokenStream> {
+    if item.trait_.is_none() {
+        return Err(Error::new_spanned(
+            &item.self_ty,
+            "`#[behavior]` can only be used on `impl MessageHandler<M> for ...`",
+        ));
+    }
+
+    let pats = args.pats.iter();
+    let fallback = match args.unhandled {
+        None => quote!(<Self as ractor::BehaviorActor>::UNHANDLED.into()),
+        Some(Unhandled::Stash) => quote!(ractor::Unhandled::Stash),
+        Some(Unhandled::DeadLetter) => quote!(ractor::Unhandled::DeadLetter),
+        Some(Unhandled::Reply(expr)) => quote!(ractor::Unhandled::Reply(#expr)),
+    };
+
+    item.items.push(syn::parse_quote! {
+        #[inline]
+        fn unhandled(
+            &self,
+            ctx: &ractor::Context<Self>,
+        ) -> ::std::option::Option<ractor::Unhandled<Self::Output>> {
+            if matches!(ctx.behavior(), #(#pats)|*) {
+                ::std::option::Option::None
+            } else {
+                ::std::option::Option::Some(#fallback)
+            }
+        }
+    });
+
+    Ok(quote!(#item))
+}
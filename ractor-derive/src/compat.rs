@@ -0,0 +1,63 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Item, ItemImpl, Path};
+
+/// 已经使用原生async fn的trait
+const NATIVE_TRAITS: &[&str] = &["Actor", "MessageHandler", "PersistentActor"];
+
+pub fn expand(attr: TokenStream, item: Item) -> TokenStream {
+    let native = match &item {
+        Item::Impl(item) => item
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| native_trait(path).map(|qualified| (item, path, qualified))),
+        _ => None,
+    };
+    match native {
+        Some((_, _, true)) => quote!(#item),
+        // 未限定的名字可能是用户自己的同名trait, 让编译器确认它来自ractor
+        Some((item_impl, path, false)) => {
+            let check = assert_ractor_trait(item_impl, path);
+            quote! {
+                #item
+                #check
+            }
+        }
+        None => quote! {
+            #[ractor::__private::async_trait(#attr)]
+            #item
+        },
+    }
+}
+
+/// 是ractor的原生trait时返回路径是否以`ractor::`限定
+fn native_trait(path: &Path) -> Option<bool> {
+    let last = path.segments.last()?;
+    if !NATIVE_TRAITS.iter().any(|name| last.ident == name) {
+        return None;
+    }
+    if path.segments.len() == 1 {
+        return Some(false);
+    }
+    if path.segments[0].ident == "ractor" {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// 原生trait都以`ractor::Actor`为supertrait, 同名的其他trait无法通过这个检查
+fn assert_ractor_trait(item: &ItemImpl, path: &Path) -> TokenStream {
+    let params = item.generics.params.iter();
+    let where_clause = &item.generics.where_clause;
+    quote_spanned! {path.span()=>
+        const _: () = {
+            #[allow(dead_code)]
+            fn only_ractor_traits_skip_async_trait<#(#params,)* __T: #path>() #where_clause {
+                fn is_actor<T: ractor::Actor>() {}
+                is_actor::<__T>();
+            }
+        };
+    }
+}
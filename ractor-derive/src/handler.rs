@@ -0,0 +1,130 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Error, FnArg, ImplItem, ImplItemMethod, ItemImpl, ReturnType, Type};
+
+/// 为`#[handler]`方法生成`impl MessageHandler<M>`
+pub fn expand(mut item: ItemImpl) -> syn::Result<TokenStream> {
+    if item.trait_.is_some() {
+        return Err(Error::new_spanned(
+            &item.self_ty,
+            "`#[handlers]` can only be used on inherent impls",
+        ));
+    }
+
+    let mut handlers = Vec::new();
+    for impl_item in &mut item.items {
+        if let ImplItem::Method(method) = impl_item {
+            let attrs = std::mem::take(&mut method.attrs);
+            let mut marker = None;
+            for attr in attrs {
+                if attr.path.is_ident("handler") {
+                    marker = Some(attr);
+                } else {
+                    method.attrs.push(attr);
+                }
+            }
+            if let Some(marker) = marker {
+                let cancellable = if marker.tokens.is_empty() {
+                    false
+                } else {
+                    let arg = marker.parse_args::<syn::Ident>()?;
+                    if arg != "cancellable" {
+                        return Err(Error::new_spanned(arg, "expected `cancellable`"));
+                    }
+                    true
+                };
+                handlers.push((method.clone(), cancellable));
+            }
+        }
+    }
+    let handlers = handlers
+        .iter()
+        .map(|(method, cancellable)| handler(&item, method, *cancellable))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #item
+        #(#handlers)*
+    })
+}
+
+fn handler(item: &ItemImpl, method: &ImplItemMethod, cancellable: bool) -> syn::Result<TokenStream> {
+    let sig = &method.sig;
+    let name = &sig.ident;
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(_)) => {}
+        _ => {
+            return Err(Error::new_spanned(
+                sig,
+                "`#[handler]` methods must take `&self` or `&mut self`",
+            ))
+        }
+    }
+    let msg = match inputs.next() {
+        Some(FnArg::Typed(arg)) => &arg.ty,
+        _ => {
+            return Err(Error::new_spanned(
+                sig,
+                "`#[handler]` methods must take the message as the first argument",
+            ))
+        }
+    };
+    // 上下文是可选的
+    let ctx = inputs.next().map(|_| format_ident!("ctx"));
+    if let Some(arg) = inputs.next() {
+        return Err(Error::new_spanned(
+            arg,
+            "`#[handler]` methods take at most the message and `&mut Context<Self>`",
+        ));
+    }
+    let output: Type = match &sig.output {
+        ReturnType::Default => syn::parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+    let call = match &ctx {
+        Some(ctx) => quote!(Self::#name(self, msg, #ctx)),
+        None => quote!(Self::#name(self, msg)),
+    };
+    let call = match sig.asyncness {
+        Some(_) => quote!(#call.await),
+        None => call,
+    };
+    let ctx = ctx.unwrap_or_else(|| format_ident!("_ctx"));
+    // `M: TypedMessage`时返回值必须是`M::Result`
+    let check = quote_spanned! {output.span()=>
+        let _: ::std::marker::PhantomData<#output> = {
+            #[allow(unused_imports)]
+            use ractor::__private::{AnyOutput as _, TypedOutput as _};
+            (&ractor::__private::OutputCheck::<#msg>(::std::marker::PhantomData)).output()
+        };
+    };
+    let cancellable = cancellable.then(|| quote!(const CANCELLABLE: bool = true;));
+
+    // 方法被cfg掉时也不生成impl
+    let cfgs = method
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"));
+
+    let self_ty = &item.self_ty;
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+    Ok(quote! {
+        #(#cfgs)*
+        impl #impl_generics ractor::MessageHandler<#msg> for #self_ty #where_clause {
+            type Output = #output;
+            #cancellable
+
+            #[inline]
+            async fn handle(
+                &mut self,
+                msg: #msg,
+                #ctx: &mut ractor::Context<Self>,
+            ) -> Self::Output {
+                #check
+                #call
+            }
+        }
+    })
+}
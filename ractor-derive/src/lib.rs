@@ -3,14 +3,122 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::DeriveInput;
 
-#[proc_macro_derive(Message)]
+mod actor;
+mod behavior;
+mod compat;
+mod handler;
+mod message;
+mod remote;
+
+/// 检查类型可以作为消息发送
+///
+/// 所有`Send`的类型都已经实现了`ractor::Message`, 这里只生成编译期的断言.
+/// `#[rtype(result = "T")]`声明响应类型, 实现`ractor::TypedMessage`.
+#[proc_macro_derive(Message, attributes(rtype))]
 pub fn message_derive(item: TokenStream) -> TokenStream {
     let derive = syn::parse_macro_input!(item as DeriveInput);
+    message::derive(derive)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// 实现`ractor::Actor`
+///
+/// `#[actor(mailbox = 100, restarts = 3, args = T, create = path)]`
+///
+/// 全部可选, `mailbox`默认为100. 没有`create`时, 指定了`args`的话通过`From<T>`创建, 否则通过`Default`创建.
+/// `create`为`async fn(&mut Context<Self>) -> Self`.
+#[proc_macro_derive(Actor, attributes(actor))]
+pub fn actor_derive(item: TokenStream) -> TokenStream {
+    let derive = syn::parse_macro_input!(item as DeriveInput);
+    actor::derive(derive)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-    let name = &derive.ident;
-    let (impl_generics, ty_generics, where_clause) = derive.generics.split_for_impl();
+/// 实现`ractor::RemoteType`, 需要`remote` feature
+///
+/// 标识为`module_path!()`加上类型名, 例如`my_crate::msg::Sum`.
+/// `#[remote(version = N)]`在后面加上`@vN`, 消息格式不兼容时增加版本.
+/// 泛型参数需要实现`RemoteType`, 它们的标识也会计入类型的标识.
+#[proc_macro_derive(RemoteType, attributes(remote))]
+pub fn remote_type_derive(item: TokenStream) -> TokenStream {
+    let derive = syn::parse_macro_input!(item as DeriveInput);
+    remote::derive(derive)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// 为impl中标记了`#[handler]`的方法生成`impl MessageHandler<M>`
+///
+/// `#[handler]`只是标记, 所在的impl必须加上`#[handlers]`, 单独使用会编译失败.
+/// 方法可以是`async fn`或普通的`fn`, 方法上的`#[cfg(...)]`同样作用于生成的impl.
+/// 方法的形式为`(&mut self, msg: M)`或`(&mut self, msg: M, ctx: &mut Context<Self>)`,
+/// 返回值就是`Output`. `#[handler(cancellable)]`见`MessageHandler::CANCELLABLE`.
+///
+/// ```ignore
+/// #[handlers]
+/// impl Counter {
+///     #[handler]
+///     async fn add(&mut self, Add(n): Add) -> u64 {
+///         self.total += n;
+///         self.total
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn handlers(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = syn::parse_macro_input!(item as syn::ItemImpl);
+    handler::expand(item)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// 标记处理消息的方法, 所在的impl必须加上[`macro@handlers`]
+///
+/// 单独使用时展开为`compile_error!`.
+#[proc_macro_attribute]
+pub fn handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = proc_macro2::TokenStream::from(item);
     (quote! {
-        impl #impl_generics ractor::message::Message for #name #ty_generics #where_clause {}
+        ::std::compile_error!("`#[handler]` must be used inside an impl marked with `#[handlers]`");
+        #item
     })
     .into()
 }
+
+/// 为无字段的枚举实现`ractor::Behavior`
+#[proc_macro_derive(Behavior)]
+pub fn behavior_derive(item: TokenStream) -> TokenStream {
+    let derive = syn::parse_macro_input!(item as DeriveInput);
+    behavior::derive(derive)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// 声明`impl MessageHandler<M>`在哪些行为下处理消息
+///
+/// `#[behavior(Phase::A | Phase::B)]`
+///
+/// `#[behavior(Phase::A, unhandled = stash | dead_letter | reply(expr))]`
+#[proc_macro_attribute]
+pub fn behavior(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(attr as behavior::BehaviorArgs);
+    let item = syn::parse_macro_input!(item as syn::ItemImpl);
+    behavior::expand(args, item)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// 从`#[async_trait]`迁移用
+///
+/// ractor的trait已经使用原生的async fn, 用于它们的impl时原样输出,
+/// 用于其他trait时转交给`async_trait::async_trait`. 只需替换`use async_trait::async_trait;`.
+///
+/// `ractor::Actor`这样限定的路径直接识别; 未限定的`Actor`, `MessageHandler`, `PersistentActor`
+/// 会额外生成一个检查, 如果是其他同名的trait则编译失败, 此时请改用`async_trait::async_trait`.
+#[proc_macro_attribute]
+pub fn async_trait(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = syn::parse_macro_input!(item as syn::Item);
+    compat::expand(attr.into(), item).into()
+}
@@ -0,0 +1,39 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Error, LitStr, Token, Type};
+
+pub fn derive(derive: DeriveInput) -> syn::Result<TokenStream> {
+    let mut result = None;
+    for attr in derive.attrs.iter().filter(|attr| attr.path.is_ident("rtype")) {
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let key = input.parse::<syn::Ident>()?;
+            if key != "result" {
+                return Err(Error::new_spanned(key, "expected `result = \"T\"`"));
+            }
+            input.parse::<Token![=]>()?;
+            result = Some(input.parse::<LitStr>()?.parse::<Type>()?);
+            Ok(())
+        })?;
+    }
+
+    let name = &derive.ident;
+    let (impl_generics, ty_generics, where_clause) = derive.generics.split_for_impl();
+    let typed = result.map(|result| {
+        quote! {
+            impl #impl_generics ractor::TypedMessage for #name #ty_generics #where_clause {
+                type Result = #result;
+            }
+        }
+    });
+
+    Ok(quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn assert_message #impl_generics () #where_clause {
+                fn is_message<T: ractor::Message>() {}
+                is_message::<#name #ty_generics>();
+            }
+        };
+        #typed
+    })
+}
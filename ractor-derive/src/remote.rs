@@ -0,0 +1,62 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Error, GenericParam, LitInt, Token};
+
+pub fn derive(mut derive: DeriveInput) -> syn::Result<TokenStream> {
+    let mut version = None;
+    for attr in derive.attrs.iter().filter(|attr| attr.path.is_ident("remote")) {
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let key = input.parse::<syn::Ident>()?;
+            if key != "version" {
+                return Err(Error::new_spanned(key, "expected `version = N`"));
+            }
+            input.parse::<Token![=]>()?;
+            version = Some(input.parse::<LitInt>()?.base10_parse::<u32>()?);
+            Ok(())
+        })?;
+    }
+
+    let name = &derive.ident;
+    // `crate::module::Type`, 带版本时为`crate::module::Type@vN`
+    let path = match version {
+        Some(version) => {
+            let version = format!("@v{}", version);
+            quote!(::std::concat!(::std::module_path!(), "::", ::std::stringify!(#name), #version))
+        }
+        None => quote!(::std::concat!(::std::module_path!(), "::", ::std::stringify!(#name))),
+    };
+
+    let params = derive
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    for param in &mut derive.generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(ractor::RemoteType));
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = derive.generics.split_for_impl();
+
+    // 泛型参数的标识也是类型标识的一部分, `Wrapper<u8>`和`Wrapper<u16>`不同
+    let (identity_ty, identity) = if params.is_empty() {
+        (quote!(&'static str), path)
+    } else {
+        let len = params.len();
+        (
+            quote!((&'static str, [u64; #len])),
+            quote!((#path, [#(<#params as ractor::RemoteType>::identity_id()),*])),
+        )
+    };
+
+    Ok(quote! {
+        impl #impl_generics ractor::RemoteType for #name #ty_generics #where_clause {
+            type Identity = #identity_ty;
+
+            #[inline]
+            fn identity() -> Self::Identity {
+                #identity
+            }
+        }
+    })
+}
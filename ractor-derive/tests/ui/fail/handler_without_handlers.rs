@@ -0,0 +1,12 @@
+use ractor::handler;
+
+struct Counter;
+
+impl Counter {
+    #[handler]
+    async fn add(&mut self, n: u64) -> u64 {
+        n
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use ractor::{handlers, Actor, Message};
+
+#[derive(Default, Actor)]
+struct Counter;
+
+#[derive(Message)]
+#[rtype(result = "u64")]
+struct Add(u64);
+
+#[handlers]
+impl Counter {
+    #[handler]
+    fn add(&mut self, Add(n): Add) -> String {
+        n.to_string()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,51 @@
+use ractor::{handlers, Actor, Broker, Context, Message};
+
+#[derive(Default, Actor)]
+struct Counter {
+    total: u64,
+}
+
+#[derive(Message)]
+#[rtype(result = "u64")]
+struct Add(u64);
+
+struct Get;
+
+struct Stop;
+
+#[handlers]
+impl Counter {
+    #[handler]
+    async fn add(&mut self, Add(n): Add) -> u64 {
+        self.total += n;
+        self.total
+    }
+
+    #[handler(cancellable)]
+    fn get(&self, _: Get) -> u64 {
+        self.total
+    }
+
+    #[handler]
+    async fn stop(&mut self, _: Stop, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+
+    #[cfg(any())]
+    #[handler]
+    fn removed(&self, _: String) -> String {
+        unreachable!()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    assert_eq!(<Counter as Actor>::MAIL_BOX_SIZE, 100);
+
+    let counter = Broker::<Counter>::spawn_one().await;
+    assert_eq!(counter.call(Add(2)).await.unwrap(), 2);
+    assert_eq!(counter.addr().caller::<Add>().send(Add(3)).await.unwrap().recv().await.unwrap(), 5);
+    assert_eq!(counter.call(Get).await.unwrap(), 5);
+    counter.call(Stop).await.unwrap();
+    counter.wait_for_actors().await;
+}
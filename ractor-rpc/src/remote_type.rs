@@ -12,15 +12,74 @@ pub trait RemoteType: Serialize + DeserializeOwned + Send {
     /// 应该保证: A.identity() == B.identity() && deserialize::<B>(serialize(A)) && deserialize::<A>(serialize(B))
     fn identity() -> Self::Identity;
 
-    // todo: optimize
+    /// 标识的哈希值, 在不同的编译和平台之间保持不变
     #[inline]
     fn identity_id() -> u64 {
-        let mut hasher = ahash::AHasher::default();
+        let mut hasher = StableHasher::new();
         Self::identity().hash(&mut hasher);
         hasher.finish()
     }
 }
 
+/// FNV-1a, 整数统一按照小端写入, `u128`以外都扩展为`u64`
+struct StableHasher(u64);
+
+impl StableHasher {
+    #[inline]
+    fn new() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i])
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(u64::from(i))
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(u64::from(i))
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    #[inline]
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128)
+    }
+}
+
 macro_rules! builtin {
     ($ty:ty, $name:expr) => {
         impl RemoteType for $ty {
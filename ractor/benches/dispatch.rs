@@ -0,0 +1,109 @@
+use std::future::Future;
+
+use criterion::async_executor::AsyncExecutor;
+use criterion::{black_box, Criterion, Throughput};
+use criterion::{criterion_group, criterion_main};
+use tokio::runtime::{Builder, Runtime};
+
+use ractor::{Actor, Broker, Context, MessageHandler};
+
+const MESSAGES: u64 = 1000;
+
+fn latency(c: &mut Criterion) {
+    for (name, rt) in runtimes() {
+        let my_actor = rt.0.block_on(Broker::<MyActor>::spawn_one());
+        let mut group = c.benchmark_group(format!("latency/{}", name));
+
+        group.bench_function("call", |b| {
+            b.to_async(&rt)
+                .iter(|| async { black_box(my_actor.call(Ping).await.unwrap()) });
+        });
+        group.bench_function("send + recv", |b| {
+            b.to_async(&rt).iter(|| async {
+                let handle = my_actor.send(Ping).await.unwrap();
+                black_box(handle.recv().await.unwrap())
+            });
+        });
+        group.finish();
+    }
+}
+
+fn throughput(c: &mut Criterion) {
+    for (name, rt) in runtimes() {
+        let my_actor = rt.0.block_on(Broker::<MyActor>::spawn_one());
+        let mut group = c.benchmark_group(format!("throughput/{}", name));
+        group.throughput(Throughput::Elements(MESSAGES));
+
+        group.bench_function("send", |b| {
+            b.to_async(&rt).iter(|| async {
+                let mut handles = Vec::with_capacity(MESSAGES as usize);
+                for _ in 0..MESSAGES {
+                    handles.push(my_actor.send(Ping).await.unwrap());
+                }
+                for handle in handles {
+                    black_box(handle.recv().await.unwrap());
+                }
+            });
+        });
+        group.bench_function("do_send", |b| {
+            b.to_async(&rt).iter(|| async {
+                for _ in 0..MESSAGES {
+                    my_actor.do_send(Ping).await.unwrap();
+                }
+                // 信箱按顺序处理, 最后一次调用返回时之前的消息都已处理完
+                black_box(my_actor.call(Ping).await.unwrap())
+            });
+        });
+        group.finish();
+    }
+}
+
+/// `multi_thread`中调用者和actor在不同的线程上, 结果包含线程间唤醒的开销;
+/// `current_thread`中两者在同一个线程上, 只有消息分发本身的开销
+fn runtimes() -> [(&'static str, TokioRt); 2] {
+    [
+        (
+            "multi_thread",
+            TokioRt(Builder::new_multi_thread().enable_all().build().unwrap()),
+        ),
+        (
+            "current_thread",
+            TokioRt(Builder::new_current_thread().enable_all().build().unwrap()),
+        ),
+    ]
+}
+
+criterion_group!(benches, latency, throughput);
+criterion_main!(benches);
+
+#[derive(Default)]
+struct MyActor;
+
+impl Actor for MyActor {
+    const MAIL_BOX_SIZE: u32 = 1024;
+    type Args = ();
+
+    async fn create(_ctx: &mut Context<Self>) -> Self
+    where
+        Self: Sized,
+    {
+        MyActor
+    }
+}
+
+struct Ping;
+
+impl MessageHandler<Ping> for MyActor {
+    type Output = ();
+
+    async fn handle(&mut self, _msg: Ping, _ctx: &mut Context<Self>) -> Self::Output {}
+}
+
+struct TokioRt(Runtime);
+
+impl AsyncExecutor for &TokioRt {
+    #[inline]
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        self.0.block_on(future)
+    }
+}
@@ -35,7 +35,6 @@ criterion_main!(benches);
 #[derive(Default)]
 struct MyActor;
 
-#[async_trait::async_trait]
 impl Actor for MyActor {
     const MAIL_BOX_SIZE: u32 = 100;
 
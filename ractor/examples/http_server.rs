@@ -33,7 +33,6 @@ async fn main() {
 #[derive(Default)]
 struct MyActor;
 
-#[async_trait::async_trait]
 impl Actor for MyActor {
     const MAIL_BOX_SIZE: u32 = 10;
     type Args = ();
@@ -49,7 +48,6 @@ impl Actor for MyActor {
 #[derive(Debug)]
 struct Req(TcpStream);
 
-#[async_trait::async_trait]
 impl MessageHandler<Req> for MyActor {
     type Output = ();
 
@@ -14,7 +14,6 @@ struct MyActor {
     id: usize,
 }
 
-#[async_trait::async_trait]
 impl Actor for MyActor {
     const MAIL_BOX_SIZE: u32 = 10;
     type Args = ();
@@ -45,13 +44,12 @@ impl Actor for MyActor {
         println!("no.{} reset", self.id);
     }
 
-    fn catch_unwind(&mut self, _err: Box<dyn Any + Send>, ctx: &mut Context<Self>) {
+    fn catch_unwind(&mut self, _err: &(dyn Any + Send), ctx: &mut Context<Self>) {
         ctx.reset();
         println!("no.{} catch_unwind", self.id)
     }
 }
 
-#[async_trait::async_trait]
 impl MessageHandler<Hello> for MyActor {
     type Output = ();
 
@@ -67,15 +65,18 @@ impl MessageHandler<Hello> for MyActor {
     }
 }
 
-#[tokio::main]
-async fn main() {
-    let my_actor = Broker::<MyActor>::spawn(2, true).await;
+fn main() {
+    ractor::rt::block_on(async {
+        let my_actor = Broker::<MyActor>::spawn(2, true).await;
 
-    my_actor.send(Hello).await.unwrap();
-    my_actor.send(Hello).await.unwrap();
-    my_actor.send(Hello).await.unwrap();
-    my_actor.send(Hello).await.unwrap();
+        my_actor.send(Hello).await.unwrap();
+        my_actor.send(Hello).await.unwrap();
+        my_actor.send(Hello).await.unwrap();
+        my_actor.send(Hello).await.unwrap();
 
-    my_actor.wait_for_actors().await;
-    stdout().flush().unwrap()
+        for exit in my_actor.wait_for_actors().await {
+            println!("{:?}", exit);
+        }
+        stdout().flush().unwrap()
+    })
 }
@@ -0,0 +1,51 @@
+use ractor::{handlers, Actor, Broker, Caller, Context, Message};
+
+#[derive(Default, Actor)]
+#[actor(mailbox = 16, restarts = 1)]
+struct Counter {
+    total: u64,
+}
+
+#[derive(Message)]
+#[rtype(result = "u64")]
+struct Add(u64);
+
+struct Get;
+
+struct Stop;
+
+#[handlers]
+impl Counter {
+    #[handler]
+    async fn add(&mut self, Add(n): Add) -> u64 {
+        self.total += n;
+        self.total
+    }
+
+    #[handler]
+    fn get(&self, _: Get) -> u64 {
+        self.total
+    }
+
+    #[handler]
+    async fn stop(&mut self, _: Stop, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+}
+
+fn main() {
+    ractor::rt::block_on(async {
+        let counter = Broker::<Counter>::spawn_one().await;
+
+        assert_eq!(counter.call(Add(2)).await.unwrap(), 2);
+        assert_eq!(counter.call(Add(3)).await.unwrap(), 5);
+        assert_eq!(counter.call(Get).await.unwrap(), 5);
+
+        // 不需要知道actor的类型
+        let caller: Caller<Add> = counter.addr().caller();
+        assert_eq!(caller.send(Add(1)).await.unwrap().recv().await.unwrap(), 6);
+
+        counter.call(Stop).await.unwrap();
+        counter.wait_for_actors().await;
+    })
+}
@@ -6,7 +6,6 @@ struct Sum;
 #[derive(Default)]
 struct MyActor;
 
-#[async_trait::async_trait]
 impl Actor for MyActor {
     const MAIL_BOX_SIZE: u32 = 100;
     type Args = ();
@@ -19,7 +18,6 @@ impl Actor for MyActor {
     }
 }
 
-#[async_trait::async_trait]
 impl MessageHandler<Sum> for MyActor {
     type Output = ();
 
@@ -28,11 +26,12 @@ impl MessageHandler<Sum> for MyActor {
     }
 }
 
-#[tokio::main]
-async fn main() {
-    let _my_actor = Broker::<MyActor>::spawn(1_000_000, true).await;
+fn main() {
+    ractor::rt::block_on(async {
+        let _my_actor = Broker::<MyActor>::spawn(1_000_000, true).await;
 
-    // 查看任务管理器, 估算占用内存大小
-    println!("done");
-    std::io::stdin().read_line(&mut String::new()).unwrap();
+        // 查看任务管理器, 估算占用内存大小
+        println!("done");
+        std::io::stdin().read_line(&mut String::new()).unwrap();
+    })
 }
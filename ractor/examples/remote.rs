@@ -1,23 +1,15 @@
-use ractor::{Actor, Context, MessageHandler, RemoteAddress, Broker};
-use ractor::{LocalAddress, MessageRegister};
-use ractor_rpc::RemoteType;
+use ractor::{Actor, Context, Message, MessageHandler, RemoteAddress, Broker};
+use ractor::{LocalAddress, MessageRegister, RemoteType};
 use url::Url;
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, Message, RemoteType)]
+#[remote(version = 1)]
+#[rtype(result = "isize")]
 struct Sum(isize, isize);
 
-impl RemoteType for Sum {
-    type Identity = &'static str;
-
-    fn identity() -> Self::Identity {
-        "example::remote::sum"
-    }
-}
-
 #[derive(Default)]
 struct MyActor;
 
-#[async_trait::async_trait]
 impl Actor for MyActor {
     const MAIL_BOX_SIZE: u32 = 10;
     type Args = ();
@@ -34,7 +26,6 @@ impl Actor for MyActor {
     }
 }
 
-#[async_trait::async_trait]
 impl MessageHandler<Sum> for MyActor {
     type Output = isize;
 
@@ -64,6 +55,6 @@ async fn main() {
 
     let mut remote_addr = RemoteAddress::connect(url).await.unwrap();
 
-    let resp = remote_addr.send::<_, MyActor>(Sum(1, 2)).await.unwrap();
+    let resp = remote_addr.send_typed(Sum(1, 2)).await.unwrap();
     assert_eq!(resp.recv().await.unwrap(), 3);
 }
@@ -8,12 +8,11 @@ struct MyActor {
     counter: usize,
 }
 
-#[async_trait::async_trait]
 impl Actor for MyActor {
     const MAIL_BOX_SIZE: u32 = 10;
     type Args = ();
 
-    async fn create(_ctx: &mut Context<Self>, _args: Self::Args) -> Self
+    async fn create(_ctx: &mut Context<Self>) -> Self
     where
         Self: Sized,
     {
@@ -28,7 +27,6 @@ impl Actor for MyActor {
     }
 }
 
-#[async_trait::async_trait]
 impl MessageHandler<Reset> for MyActor {
     type Output = usize;
 
@@ -41,13 +39,14 @@ impl MessageHandler<Reset> for MyActor {
     }
 }
 
-#[tokio::main]
-async fn main() {
-    let my_actor = Broker::<MyActor>::spawn_one().await;
+fn main() {
+    ractor::rt::block_on(async {
+        let my_actor = Broker::<MyActor>::spawn_one().await;
 
-    assert_eq!(my_actor.call(Reset).await.unwrap(), 0);
-    assert_eq!(my_actor.call(Reset).await.unwrap(), 1);
-    assert_eq!(my_actor.call(Reset).await.unwrap(), 2);
-    assert_eq!(my_actor.call(Reset).await.unwrap(), 3);
-    assert_eq!(my_actor.call(Reset).await.unwrap(), 3);
+        assert_eq!(my_actor.call(Reset).await.unwrap(), 0);
+        assert_eq!(my_actor.call(Reset).await.unwrap(), 1);
+        assert_eq!(my_actor.call(Reset).await.unwrap(), 2);
+        assert_eq!(my_actor.call(Reset).await.unwrap(), 3);
+        assert_eq!(my_actor.call(Reset).await.unwrap(), 3);
+    })
 }
@@ -0,0 +1,51 @@
+//! 在不同的运行时上运行同一个actor
+//!
+//! ```text
+//! cargo run --example runtimes
+//! cargo run --example runtimes --no-default-features --features rt-async-std
+//! cargo run --example runtimes --no-default-features --features rt-smol
+//! ```
+
+use std::time::Duration;
+
+use ractor::{Actor, Broker, Context, MessageHandler};
+
+struct Ping;
+
+struct MyActor {
+    count: usize,
+}
+
+impl Actor for MyActor {
+    const MAIL_BOX_SIZE: u32 = 10;
+    type Args = ();
+
+    async fn create(_ctx: &mut Context<Self>) -> Self
+    where
+        Self: Sized,
+    {
+        MyActor { count: 0 }
+    }
+}
+
+impl MessageHandler<Ping> for MyActor {
+    type Output = usize;
+
+    async fn handle(&mut self, _: Ping, ctx: &mut Context<Self>) -> Self::Output {
+        self.count += 1;
+        ctx.sleep(Duration::from_millis(10));
+        self.count
+    }
+}
+
+async fn run() {
+    let broker = Broker::<MyActor>::spawn_one().await;
+    for _ in 0..3 {
+        println!("pong {}", broker.call(Ping).await.unwrap());
+    }
+    broker.wait_for_actors().await;
+}
+
+fn main() {
+    ractor::rt::block_on(run())
+}
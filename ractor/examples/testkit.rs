@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use ractor::testkit::{TestActor, TestProbe};
+use ractor::{Actor, Context, MessageHandler};
+
+#[derive(Debug)]
+struct Tick;
+
+#[derive(Debug, PartialEq)]
+struct Ticked(usize);
+
+#[derive(Default)]
+struct MyActor {
+    ticks: usize,
+}
+
+impl Actor for MyActor {
+    const MAIL_BOX_SIZE: u32 = 10;
+    type Args = ();
+
+    async fn create(_ctx: &mut Context<Self>) -> Self
+    where
+        Self: Sized,
+    {
+        MyActor::default()
+    }
+}
+
+impl MessageHandler<Tick> for MyActor {
+    type Output = usize;
+
+    async fn handle(&mut self, _: Tick, ctx: &mut Context<Self>) -> Self::Output {
+        self.ticks += 1;
+        // 使用暂停时间时, 休眠会立即完成
+        ctx.sleep(Duration::from_secs(60));
+        self.ticks
+    }
+}
+
+// Messages are handled only when `step` is called, and with the paused clock
+// the 60 second sleep after each tick completes immediately.
+#[tokio::main(flavor = "current_thread", start_paused = true)]
+async fn main() {
+    let mut my_actor = TestActor::<MyActor>::new(()).await;
+
+    let resp = my_actor.send(Tick).await;
+    assert_eq!(my_actor.actor().ticks, 0);
+    assert!(my_actor.step().await);
+    assert_eq!(resp.recv().await.unwrap(), 1);
+    assert_eq!(my_actor.call(Tick).await, 2);
+    assert!(!my_actor.step().await);
+
+    let mut probe = TestProbe::<Ticked>::new().await;
+    probe.addr().send(Ticked(my_actor.actor().ticks)).await.unwrap();
+    assert_eq!(probe.expect_msg().await, Ticked(2));
+    probe.expect_no_msg(Duration::from_secs(1)).await;
+}
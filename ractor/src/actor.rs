@@ -1,10 +1,31 @@
 use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::sync::Mutex;
 
 use crate::actor_runner::StoppingPosition;
 use crate::context::Context;
 use crate::State;
 
-#[async_trait::async_trait]
+/// actor的生命周期
+///
+/// 生命周期方法都返回`impl Future + Send`, 实现时直接写`async fn`即可, future不会被装箱.
+///
+/// 从`#[async_trait]`迁移时删除impl上的属性, 或者把`async_trait::async_trait`
+/// 换成[`ractor::async_trait`](crate::async_trait), 它会原样保留ractor的trait的impl.
+///
+/// 所有`Actor`都自动实现了[`UnsendActor`], 不能满足`Send`的actor直接实现[`UnsendActor`].
+///
+/// ```ignore
+/// impl Actor for MyActor {
+///     const MAIL_BOX_SIZE: u32 = 10;
+///     type Args = ();
+///
+///     async fn create(_ctx: &mut Context<Self>) -> Self {
+///         MyActor
+///     }
+/// }
+/// ```
 pub trait Actor: Send + 'static {
     /// 信箱大小
     const MAIL_BOX_SIZE: u32;
@@ -12,26 +33,73 @@ pub trait Actor: Send + 'static {
     /// 最大重试次数
     const MAX_RESTARTS: u16 = 3;
 
+    /// 消息暂存区大小, 见[`Context::stash`]
+    const STASH_CAPACITY: usize = 64;
+
+    /// 重置时是否保留暂存的消息
+    ///
+    /// 默认在[`Actor::reset`]之前清空暂存区.
+    const KEEP_STASH_ON_RESET: bool = false;
+
     type Args: Send + Sync + Clone;
 
-    async fn create(_ctx: &mut Context<Self>) -> Self
+    fn create(_ctx: &mut Context<Self>) -> impl Future<Output = Self> + Send
     where
         Self: Sized;
 
-    async fn started(&mut self, _ctx: &mut Context<Self>) {}
+    fn started(&mut self, _ctx: &mut Context<Self>) -> impl Future<Output = ()> + Send {
+        async {}
+    }
 
-    async fn stopped(&mut self, _ctx: &mut Context<Self>, _pos: StoppingPosition) {}
+    /// 停止之前调用, 返回[`Running::Continue`]时继续运行
+    ///
+    /// 信箱已关闭([`StoppingPosition::End`])时不会调用, 无论如何都会停止.
+    fn stopping(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _pos: StoppingPosition,
+    ) -> impl Future<Output = Running> + Send {
+        async { Running::Stop }
+    }
+
+    fn stopped(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _pos: StoppingPosition,
+    ) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// 重启之前, 在[`Actor::reset`]之前调用
+    fn pre_restart(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _reason: &RestartReason,
+    ) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// 重启之后, 在[`Actor::reset`]之后调用
+    fn post_restart(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _reason: &RestartReason,
+    ) -> impl Future<Output = ()> + Send {
+        async {}
+    }
 
     /// 清理并重置actor的状态
     ///
     ///
     /// 默认行为为调用[`Actor::create`]创建一个新的Actor替换当前Self.
-    async fn reset(&mut self, _ctx: &mut Context<Self>)
+    fn reset(&mut self, _ctx: &mut Context<Self>) -> impl Future<Output = ()> + Send
     where
         Self: Sized,
     {
-        let new = Self::create(_ctx).await;
-        *self = new;
+        async move {
+            let new = Self::create(_ctx).await;
+            *self = new;
+        }
     }
 
     #[cfg(feature = "remote")]
@@ -54,7 +122,12 @@ pub trait Actor: Send + 'static {
     ///
     ///
     /// 最大重启次数视乎[`Actor::MAX_RESTARTS`], 到达限制之后无论如何都会结束, 避免意外错误导致的无限重启和重启带来的性能损耗.
-    fn catch_unwind(&mut self, _err: Box<dyn Any + Send>, ctx: &mut Context<Self>) {
+    ///
+    /// 重启时`err`会以[`RestartReason::Panic`]交给[`Actor::pre_restart`]和[`Actor::post_restart`].
+    ///
+    /// 0.1.3之前`err`为`Box<dyn Any + Send>`, 现在只借用panic的内容,
+    /// 所有权留给重启流程. 之前的实现把参数改为`&(dyn Any + Send)`即可, `downcast_ref`的用法不变.
+    fn catch_unwind(&mut self, _err: &(dyn Any + Send), ctx: &mut Context<Self>) {
         ctx.state = State::Reset
     }
 
@@ -66,3 +139,216 @@ pub trait Actor: Send + 'static {
         register
     }
 }
+
+/// 不要求`Send`的actor, [`Actor`]的基础
+///
+/// 和[`Actor`]的区别只在于actor本身和生命周期方法返回的future都不需要`Send`.
+/// [`Context`]和运行actor的流程是共用的, 所有[`Actor`]都自动实现了这个trait,
+/// 直接实现它的actor只能由[`UnsendBroker`](crate::unsend::UnsendBroker)生成在当前线程上.
+///
+/// 各个方法的含义见[`Actor`]中的同名方法.
+pub trait UnsendActor: 'static {
+    /// 信箱大小
+    const MAIL_BOX_SIZE: u32;
+
+    /// 最大重试次数
+    const MAX_RESTARTS: u16 = 3;
+
+    /// 消息暂存区大小, 见[`Context::stash`]
+    const STASH_CAPACITY: usize = 64;
+
+    /// 重置时是否保留暂存的消息
+    const KEEP_STASH_ON_RESET: bool = false;
+
+    type Args: Send + Sync + Clone;
+
+    fn create(ctx: &mut Context<Self>) -> impl Future<Output = Self>
+    where
+        Self: Sized;
+
+    fn started(&mut self, _ctx: &mut Context<Self>) -> impl Future<Output = ()> {
+        async {}
+    }
+
+    fn stopping(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _pos: StoppingPosition,
+    ) -> impl Future<Output = Running> {
+        async { Running::Stop }
+    }
+
+    fn stopped(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _pos: StoppingPosition,
+    ) -> impl Future<Output = ()> {
+        async {}
+    }
+
+    fn pre_restart(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _reason: &RestartReason,
+    ) -> impl Future<Output = ()> {
+        async {}
+    }
+
+    fn post_restart(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _reason: &RestartReason,
+    ) -> impl Future<Output = ()> {
+        async {}
+    }
+
+    fn reset(&mut self, ctx: &mut Context<Self>) -> impl Future<Output = ()>
+    where
+        Self: Sized,
+    {
+        async move {
+            *self = Self::create(ctx).await;
+        }
+    }
+
+    fn catch_unwind(&mut self, _err: &(dyn Any + Send), ctx: &mut Context<Self>) {
+        ctx.state = State::Reset
+    }
+}
+
+impl<A: ?Sized> UnsendActor for A
+where
+    A: Actor,
+{
+    const MAIL_BOX_SIZE: u32 = <A as Actor>::MAIL_BOX_SIZE;
+    const MAX_RESTARTS: u16 = <A as Actor>::MAX_RESTARTS;
+    const STASH_CAPACITY: usize = <A as Actor>::STASH_CAPACITY;
+    const KEEP_STASH_ON_RESET: bool = <A as Actor>::KEEP_STASH_ON_RESET;
+
+    type Args = <A as Actor>::Args;
+
+    #[inline]
+    fn create(ctx: &mut Context<Self>) -> impl Future<Output = Self>
+    where
+        Self: Sized,
+    {
+        <A as Actor>::create(ctx)
+    }
+
+    #[inline]
+    fn started(&mut self, ctx: &mut Context<Self>) -> impl Future<Output = ()> {
+        <A as Actor>::started(self, ctx)
+    }
+
+    #[inline]
+    fn stopping(
+        &mut self,
+        ctx: &mut Context<Self>,
+        pos: StoppingPosition,
+    ) -> impl Future<Output = Running> {
+        <A as Actor>::stopping(self, ctx, pos)
+    }
+
+    #[inline]
+    fn stopped(
+        &mut self,
+        ctx: &mut Context<Self>,
+        pos: StoppingPosition,
+    ) -> impl Future<Output = ()> {
+        <A as Actor>::stopped(self, ctx, pos)
+    }
+
+    #[inline]
+    fn pre_restart(
+        &mut self,
+        ctx: &mut Context<Self>,
+        reason: &RestartReason,
+    ) -> impl Future<Output = ()> {
+        <A as Actor>::pre_restart(self, ctx, reason)
+    }
+
+    #[inline]
+    fn post_restart(
+        &mut self,
+        ctx: &mut Context<Self>,
+        reason: &RestartReason,
+    ) -> impl Future<Output = ()> {
+        <A as Actor>::post_restart(self, ctx, reason)
+    }
+
+    #[inline]
+    fn reset(&mut self, ctx: &mut Context<Self>) -> impl Future<Output = ()>
+    where
+        Self: Sized,
+    {
+        <A as Actor>::reset(self, ctx)
+    }
+
+    #[inline]
+    fn catch_unwind(&mut self, err: &(dyn Any + Send), ctx: &mut Context<Self>) {
+        <A as Actor>::catch_unwind(self, err, ctx)
+    }
+}
+
+/// [`Actor::stopping`]的返回值
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Running {
+    Stop,
+    Continue,
+}
+
+/// 重启的原因
+#[derive(Debug)]
+pub enum RestartReason {
+    /// 生命周期中发生了panic, 见[`Actor::catch_unwind`]
+    Panic(PanicPayload),
+    /// 调用了[`Context::reset`]
+    Reset,
+}
+
+/// panic的内容
+///
+/// 包装在`Mutex`中, 使[`RestartReason`]可以在异步方法之间共享.
+pub struct PanicPayload(Mutex<Box<dyn Any + Send>>);
+
+impl PanicPayload {
+    #[inline]
+    pub(crate) fn new(payload: Box<dyn Any + Send>) -> Self {
+        PanicPayload(Mutex::new(payload))
+    }
+
+    /// panic的信息, 只支持`&str`和`String`
+    pub fn message(&self) -> Option<String> {
+        self.with(panic_message)
+    }
+
+    /// 访问panic的原始内容
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&(dyn Any + Send)) -> R,
+    {
+        let payload = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        f(&**payload)
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Box<dyn Any + Send> {
+        self.0.into_inner().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    payload
+        .downcast_ref::<&str>()
+        .map(|msg| msg.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+}
+
+impl Debug for PanicPayload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.message() {
+            Some(msg) => write!(f, "{:?}", msg),
+            None => write!(f, ".."),
+        }
+    }
+}
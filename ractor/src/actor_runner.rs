@@ -1,123 +1,428 @@
+use std::any::Any;
 use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future::{select, Either};
 use futures::FutureExt;
+use tokio::time::Instant;
 
-use crate::actor::Actor;
+use crate::actor::{panic_message, PanicPayload, RestartReason, Running, UnsendActor};
 use crate::context::Context;
+use crate::envelope::{Envelope, FutureSlot};
+use crate::interceptor::MessageInfo;
+use crate::introspect::{ActorStatus, Phase};
 use crate::State;
 
-pub struct ActorRunner<A> where A: Actor {
+pub struct ActorRunner<A> where A: UnsendActor {
     pub actor: A,
     pub context: Context<A>,
+    /// 处理消息的future, 在消息之间复用
+    pub(crate) slot: FutureSlot,
+    /// 最后一次[`Actor::stopped`](crate::Actor::stopped)的位置
+    pub(crate) position: Option<StoppingPosition>,
+    /// 最后一次panic的信息
+    pub(crate) panic: Option<String>,
+    pub(crate) restarts_exhausted: bool,
+    /// 在专用线程上运行, 阻塞等待信箱中的消息
+    blocking: bool,
 }
-macro_rules! reach_state {
-    ($state:expr, { $($p:pat_param => $c:expr),* }) => {
-        match $state {
-            $($p => $c),*,
-            State::Pause(notify) => {
-                notify.notified().await;
-            }
-            State::Yield => {
-                tokio::task::yield_now().await;
-            }
-            State::Sleep(t) => tokio::time::sleep(std::time::Duration::from_millis(*t)).boxed().await,
-            State::Abort => {}
-        }
-        $state.clear();
-    };
+
+/// actor在生命周期中的位置, 决定[`State`]的效果
+#[derive(Copy, Clone)]
+pub(crate) enum Stage {
+    /// 进入生命周期之后, 调用[`Actor::started`](crate::Actor::started)之前
+    Created,
+    /// 调用[`Actor::started`](crate::Actor::started)之后
+    Started,
+    /// 处理完一条消息之后
+    Handled,
+    /// 调用[`Actor::stopped`](crate::Actor::stopped)之后
+    Stopped(StoppingPosition),
+}
+
+/// [`ActorRunner::settle`]之后的下一步
+pub(crate) enum Transition {
+    /// 继续处理消息
+    Continue,
+    /// 已经重置, 重新进入生命周期
+    Restart,
+    /// 调用[`Actor::stopped`](crate::Actor::stopped)
+    Stop(StoppingPosition),
+    /// 结束, 不再调用生命周期方法
+    End,
 }
 
 impl<A> ActorRunner<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
     #[inline]
-    pub async fn run(mut self) -> () {
-        let mut restart_count = 0;
-
-        'main_loop: loop {
-            match AssertUnwindSafe(async {
-                'life_cycle: loop {
-                    // 进入生命周期后的状态
-                    reach_state!(&mut self.context.state, {
-                        State::Continue => {},
-                        State::Reset => {
-                            // 不能在start之前就reset
-                        },
-                        State::Stop => break 'life_cycle
-                    });
-                    self.actor.started(&mut self.context).await;
-
-                    let pos = 'started: loop {
-                        // 开始之后的状态
-                        reach_state!(&mut self.context.state, {
-                            State::Continue => {},
-                            State::Stop => break 'started StoppingPosition::Starting,
-                            State::Reset => {
-                                self.actor.reset(&mut self.context).await;
-                                continue 'life_cycle;
-                            }
-                        });
-
-                        #[allow(unused_labels)]
-                        'message_loop: while let Ok(envelope) = self.context.recipient.recv().await
-                        {
-                            (envelope)(&mut self.actor, &mut self.context).await;
-
-                            // 处理完消息之后的状态
-                            reach_state!(&mut self.context.state, {
-                                State::Continue => {},
-                                State::Reset => {
-                                    self.actor.reset(&mut self.context).await;
-                                    continue 'life_cycle;
-                                },
-                                State::Stop => {
-                                    break 'started StoppingPosition::Message;
-                                }
-                            });
-                        }
-                        break 'started StoppingPosition::End;
-                    };
-                    self.actor.stopped(&mut self.context, pos).await;
-                    // 停止之后的状态
-                    reach_state!(&mut self.context.state, {
-                        State::Continue => {},
-                        State::Stop => {},
-                        State::Reset => {
-                            // 如果消息通道关闭了, 那么就不可能再重启
-                            if !matches!(pos, StoppingPosition::End) {
-                                self.actor.reset(&mut self.context).await;
-                                continue 'life_cycle;
-                            }
-                        }
-                    });
-                    break 'life_cycle;
-                }
-            })
-            .catch_unwind()
-            .await
-            {
-                Ok(_) => break 'main_loop,
+    pub fn new(actor: A, context: Context<A>) -> Self {
+        ActorRunner::with_slot(actor, context, FutureSlot::new())
+    }
+
+    /// 可以处理[`UnsendHandler`](crate::unsend::UnsendHandler)的消息, 只能在`spawn_local`中运行
+    #[inline]
+    pub(crate) fn local(actor: A, context: Context<A>) -> Self {
+        ActorRunner::with_slot(actor, context, FutureSlot::local())
+    }
+
+    #[inline]
+    fn with_slot(actor: A, context: Context<A>, slot: FutureSlot) -> Self {
+        ActorRunner {
+            actor,
+            context,
+            slot,
+            position: None,
+            panic: None,
+            restarts_exhausted: false,
+            blocking: false,
+        }
+    }
+
+    /// 只能在专用线程上运行, 见[`SyncBroker`](crate::SyncBroker)
+    #[cfg(feature = "rt-tokio")]
+    #[inline]
+    pub(crate) fn blocking(actor: A, context: Context<A>) -> Self {
+        ActorRunner {
+            blocking: true,
+            ..ActorRunner::new(actor, context)
+        }
+    }
+
+    pub async fn run(mut self) -> ActorExit {
+        loop {
+            match AssertUnwindSafe(self.drive()).catch_unwind().await {
+                Ok(()) => break,
                 Err(err) => {
-                    self.context.state = State::Abort;
-                    self.actor.catch_unwind(err, &mut self.context);
-                    if matches!(self.context.state, State::Reset) && restart_count < A::MAX_RESTARTS
-                    {
-                        restart_count += 1;
-                        self.actor.reset(&mut self.context).await;
-                        continue 'main_loop;
-                    } else {
-                        break 'main_loop;
+                    if !self.recover(err).await {
+                        break;
                     }
                 }
             }
         }
+        self.context.status.set_phase(Phase::Stopped);
+        ActorExit {
+            id: self.context.status.id(),
+            position: self.position,
+            restarts: self.context.status.restarts(),
+            panic_restarts: self.context.status.panic_restarts(),
+            restarts_exhausted: self.restarts_exhausted,
+            panic: self.panic,
+            aborted: false,
+        }
+    }
+
+    /// 从头开始生命周期, 依次处理信箱中的消息直到结束
+    async fn drive(&mut self) {
+        let mut next = self.begin().await;
+        while self.advance(next).await {
+            next = match self.next_envelope().await {
+                Some(envelope) => self.step(envelope).await,
+                None => self.finish(StoppingPosition::End).await,
+            };
+        }
+    }
+
+    /// 进入生命周期并调用[`Actor::started`](crate::Actor::started)
+    pub(crate) async fn begin(&mut self) -> Transition {
+        self.position = None;
+        if let Transition::End = self.settle(Stage::Created).await {
+            return Transition::End;
+        }
+        self.context.status.set_phase(Phase::Starting);
+        self.actor.started(&mut self.context).await;
+        self.context.stash.flush();
+        self.context.status.set_phase(Phase::Idle);
+        self.settle(Stage::Started).await
+    }
+
+    /// 处理一条消息
+    pub(crate) async fn step(&mut self, envelope: Envelope<A>) -> Transition {
+        self.handle(envelope).await;
+        self.context.stash.flush();
+        self.settle(Stage::Handled).await
+    }
+
+    /// 调用[`Actor::stopped`](crate::Actor::stopped)
+    pub(crate) async fn finish(&mut self, pos: StoppingPosition) -> Transition {
+        self.context.status.set_phase(Phase::Stopping);
+        self.actor.stopped(&mut self.context, pos).await;
+        self.position = Some(pos);
+        self.settle(Stage::Stopped(pos)).await
+    }
+
+    /// 执行重启和停止, 直到可以继续处理消息(返回`true`)或者结束(返回`false`)
+    pub(crate) async fn advance(&mut self, mut next: Transition) -> bool {
+        loop {
+            next = match next {
+                Transition::Continue => return true,
+                Transition::End => return false,
+                Transition::Restart => self.begin().await,
+                Transition::Stop(pos) => self.finish(pos).await,
+            };
+        }
+    }
+
+    /// 在`stage`处执行[`Context::state`]
+    pub(crate) async fn settle(&mut self, stage: Stage) -> Transition {
+        self.context.status.set_state(&self.context.state);
+        let proceed = match stage {
+            Stage::Stopped(_) => Transition::End,
+            _ => Transition::Continue,
+        };
+        let next = match &self.context.state {
+            State::Continue | State::Abort => proceed,
+            State::Stop => match stage {
+                Stage::Started => self.stop_at(StoppingPosition::Starting).await,
+                Stage::Handled => self.stop_at(StoppingPosition::Message).await,
+                Stage::Created | Stage::Stopped(_) => Transition::End,
+            },
+            State::Reset => match stage {
+                // 不能在start之前就reset
+                Stage::Created => Transition::Continue,
+                // 如果消息通道关闭了, 那么就不可能再重启
+                Stage::Stopped(StoppingPosition::End) => Transition::End,
+                _ => {
+                    self.reset(RestartReason::Reset).await;
+                    // 重置过程中设置的状态在重新进入生命周期时执行
+                    return Transition::Restart;
+                }
+            },
+            State::Pause(notify) => {
+                Arc::clone(notify).notified().await;
+                proceed
+            }
+            State::Yield => {
+                crate::rt::yield_now().await;
+                proceed
+            }
+            State::Sleep(t) => {
+                crate::rt::sleep(Duration::from_millis(*t)).await;
+                proceed
+            }
+        };
+        self.context.state.clear();
+        self.context.status.set_state(&self.context.state);
+        next
+    }
+
+    async fn stop_at(&mut self, pos: StoppingPosition) -> Transition {
+        if self.should_stop(pos).await {
+            Transition::Stop(pos)
+        } else {
+            Transition::Continue
+        }
+    }
+
+    /// 交给[`Actor::catch_unwind`](crate::Actor::catch_unwind)处理panic, 返回是否已经重启
+    pub(crate) async fn recover(&mut self, err: Box<dyn Any + Send>) -> bool {
+        self.position = None;
+        self.panic = Some(panic_message(&*err).unwrap_or_default());
+        self.context.state = State::Abort;
+        self.actor.catch_unwind(&*err, &mut self.context);
+        if !matches!(self.context.state, State::Reset) {
+            return false;
+        }
+        if self.context.status.panic_restarts() >= u32::from(A::MAX_RESTARTS) {
+            self.restarts_exhausted = true;
+            return false;
+        }
+        self.context.status.add_panic_restart();
+        self.reset(RestartReason::Panic(PanicPayload::new(err)))
+            .await;
+        true
+    }
+
+    /// 依次从暂存区, 已完成的[`Context::defer`]任务和信箱中取出消息
+    async fn next_envelope(&mut self) -> Option<Envelope<A>> {
+        if let Some(envelope) = self.context.stash.next_unstashed() {
+            return Some(envelope);
+        }
+
+        let Context {
+            global_context,
+            deferred,
+            ..
+        } = &mut self.context;
+        if deferred.is_blocking() {
+            if let Some(envelope) = deferred.next().await {
+                return Some(envelope);
+            }
+        }
+
+        loop {
+            let envelope = if deferred.is_empty() && self.blocking {
+                // 没有需要等待的任务时, 直接阻塞在信箱上
+                global_context.intake.wait().await;
+                global_context.recipient.recv_blocking().ok()?
+            } else {
+                // 暂停时不从信箱中取出消息
+                let recv = async {
+                    global_context.intake.wait().await;
+                    global_context.recipient.recv().await
+                };
+                if deferred.is_empty() {
+                    recv.await.ok()?
+                } else {
+                    let next = deferred.next();
+                    futures::pin_mut!(recv, next);
+                    match select(next, recv).await {
+                        Either::Left((Some(envelope), _)) => return Some(envelope),
+                        // 有序任务panic, 重新等待
+                        Either::Left((None, _)) => continue,
+                        Either::Right((Ok(envelope), _)) => envelope,
+                        // 信箱已关闭, 等待剩下的任务完成
+                        Either::Right((Err(_), next)) => return next.await,
+                    }
+                }
+            };
+            self.received().await;
+            return Some(envelope);
+        }
+    }
+
+    /// 不等待, 依次从暂存区, 已完成的[`Context::defer`]任务和信箱中取出已经就绪的消息
+    #[cfg(feature = "testkit")]
+    pub(crate) async fn try_next_envelope(&mut self) -> Option<Envelope<A>> {
+        if let Some(envelope) = self.context.stash.next_unstashed() {
+            return Some(envelope);
+        }
+        if let Some(envelope) = self.context.deferred.next().now_or_never().flatten() {
+            return Some(envelope);
+        }
+        if self.context.deferred.is_blocking() || self.context.is_paused() {
+            return None;
+        }
+        let envelope = self.context.recipient.try_recv().ok()?;
+        self.received().await;
+        Some(envelope)
+    }
+
+    /// 从信箱中取出消息之后执行
+    async fn received(&mut self) {
+        self.context.intake.notify_space();
+        // 等待信箱期间被暂停的话, 取出的消息也要等到恢复之后才处理
+        self.context.intake.admit().await;
+    }
+
+    /// 处理一条消息
+    pub(crate) async fn handle(&mut self, mut envelope: Envelope<A>) {
+        self.context
+            .status
+            .set_current_message(Some(envelope.message_type()));
+        self.context.meta = envelope.take_meta();
+        self.context.deferred.discard_reply();
+        self.intercept(envelope).await;
+        self.context.meta = None;
+        self.context.status.set_current_message(None);
+    }
+
+    /// 前后执行拦截器
+    async fn intercept(&mut self, envelope: Envelope<A>) {
+        let interceptors = match self.context.global_context.intake.interceptors() {
+            Some(interceptors) => interceptors,
+            None => {
+                return envelope
+                    .handle(&mut self.actor, &mut self.context, &mut self.slot)
+                    .await
+            }
+        };
+
+        let info = MessageInfo {
+            actor_type: std::any::type_name::<A>(),
+            message_type: envelope.message_type(),
+            received_at: Instant::now(),
+        };
+        for interceptor in interceptors.iter() {
+            if let Err(rejection) = interceptor.before(&info) {
+                log::debug!("`{}` is discarded. {}", info.message_type, rejection);
+                return;
+            }
+        }
+        envelope
+            .handle(&mut self.actor, &mut self.context, &mut self.slot)
+            .await;
+        for interceptor in interceptors.iter().rev() {
+            interceptor.after(&info);
+        }
+    }
+
+    /// 调用[`Actor::stopping`](crate::Actor::stopping), 返回是否停止
+    pub(crate) async fn should_stop(&mut self, pos: StoppingPosition) -> bool {
+        self.actor.stopping(&mut self.context, pos).await == Running::Stop
+    }
+
+    pub(crate) async fn reset(&mut self, reason: RestartReason) {
+        self.actor.pre_restart(&mut self.context, &reason).await;
+        if !A::KEEP_STASH_ON_RESET {
+            self.context.stash.clear();
+        }
+        self.context.behaviors.clear();
+        self.context.deferred.clear();
+        self.context.meta = None;
+        self.context.cancellation = None;
+        self.context.status.set_current_message(None);
+        self.context.status.add_restart();
+        self.actor.reset(&mut self.context).await;
+        self.actor.post_restart(&mut self.context, &reason).await;
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum StoppingPosition {
     Starting,
     Message,
     End,
 }
+
+/// actor结束时的情况, 见[`Broker::wait_for_actors`](crate::Broker::wait_for_actors)
+#[derive(Clone, Debug)]
+pub struct ActorExit {
+    /// 在所属broker中的编号
+    pub id: usize,
+    /// 最后一次[`Actor::stopped`](crate::Actor::stopped)的位置, 没有执行`stopped`时为`None`
+    ///
+    /// 例如panic之后没有重启, 或者在[`Actor::started`](crate::Actor::started)之前就停止.
+    pub position: Option<StoppingPosition>,
+    /// 重启次数, 包括[`State::Reset`]
+    pub restarts: u32,
+    /// panic之后的重启次数, 即计入[`Actor::MAX_RESTARTS`](crate::Actor::MAX_RESTARTS)的部分
+    pub panic_restarts: u32,
+    /// [`Actor::catch_unwind`](crate::Actor::catch_unwind)要求重启, 但已经达到[`Actor::MAX_RESTARTS`](crate::Actor::MAX_RESTARTS)
+    pub restarts_exhausted: bool,
+    /// 最后一次panic的信息, 不是字符串的panic为空字符串
+    ///
+    /// 包括在[`Actor::catch_unwind`](crate::Actor::catch_unwind)和重启过程中发生的panic.
+    pub panic: Option<String>,
+    /// 被[`Broker::abort`](crate::Broker::abort)停止
+    pub aborted: bool,
+}
+
+impl ActorExit {
+    /// 任务被停止或者在捕获panic之外panic, 只能从[`ActorStatus`]得到结果
+    pub(crate) fn interrupted(status: Option<&ActorStatus>, panic: Option<String>) -> Self {
+        ActorExit {
+            id: status.map_or(0, ActorStatus::id),
+            position: None,
+            restarts: status.map_or(0, ActorStatus::restarts),
+            panic_restarts: status.map_or(0, ActorStatus::panic_restarts),
+            restarts_exhausted: false,
+            aborted: panic.is_none(),
+            panic,
+        }
+    }
+
+    /// 是否执行了[`Actor::stopped`](crate::Actor::stopped)
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// 正常结束, 即执行了[`Actor::stopped`](crate::Actor::stopped)且没有被停止
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.is_stopped() && !self.aborted
+    }
+}
@@ -1,25 +1,100 @@
 use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
 use thiserror::Error;
 
 #[cfg(feature = "remote")]
 use crate::address::remote::RemoteAddressServer;
+use crate::breaker::{BreakerConfig, CircuitBreaker};
 use crate::envelope::{self, Envelope, MailBoxTx};
 use crate::error::{ChannelSendError, ChannelTrySendError};
-use crate::message::{HandlerPanic, Message};
+use crate::message::{HandlerPanic, Message, TypedMessage};
+use crate::meta::EnvelopeMeta;
+use crate::recipient::{Caller, Recipient};
+use crate::intake::Intake;
+use crate::interceptor::Interceptor;
+use crate::limiter::{RateLimiter, Throttled};
+use crate::retry::{CallErrorKind, RetryPolicy};
+use crate::actor::UnsendActor;
 use crate::{Actor, MessageHandler, ResponseHandle};
 
-pub struct LocalAddress<A: ?Sized> where A: Actor {
+pub struct LocalAddress<A: ?Sized> where A: UnsendActor {
     pub(crate) sender: MailBoxTx<A>,
+    pub(crate) intake: Arc<Intake>,
 }
 
 impl<A> LocalAddress<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
     #[inline]
     pub fn new(sender: MailBoxTx<A>) -> Self {
-        LocalAddress { sender }
+        LocalAddress {
+            sender,
+            intake: Default::default(),
+        }
+    }
+
+    /// 暂停这个地址的全部actor
+    ///
+    /// actor处理完当前消息之后不再从信箱中取出新的消息, 但依然可以发送消息到信箱中,
+    /// 直到[`LocalAddress::resume`].
+    ///
+    /// 对[`Context::unstash_all`]取出的消息和[`Context::defer`]的任务没有影响.
+    #[inline]
+    pub fn pause(&self) {
+        self.intake.pause()
+    }
+
+    #[inline]
+    pub fn resume(&self) {
+        self.intake.resume()
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.intake.is_paused()
+    }
+
+    /// 限制这个地址的全部actor从信箱中取出消息的速度, `None`表示取消限制
+    ///
+    /// 使用[`LimitMode::Reject`](crate::limiter::LimitMode::Reject)时, 超出限制的消息不会进入信箱,
+    /// 发送时返回[`ChannelSendError::RateLimited`]或[`ChannelTrySendError::RateLimited`], 可以取回消息.
+    #[inline]
+    pub fn set_rate_limit(&self, limiter: Option<RateLimiter>) {
+        self.intake.set_rate_limiter(limiter)
+    }
+
+    /// 添加一个拦截器, 见[`Interceptor`]
+    #[inline]
+    pub fn intercept<I>(&self, interceptor: I)
+    where
+        I: Interceptor,
+    {
+        self.intake.add_interceptor(Arc::new(interceptor))
+    }
+
+    /// 移除全部拦截器
+    #[inline]
+    pub fn clear_interceptors(&self) {
+        self.intake.clear_interceptors()
+    }
+}
+
+impl<A> LocalAddress<A>
+where
+    A: Actor,
+{
+    /// 限制通过返回的地址发送消息的速度
+    #[inline]
+    pub fn throttle(self, limiter: RateLimiter) -> Throttled<A> {
+        Throttled::new(self, limiter)
+    }
+
+    /// 通过熔断器调用, 见[`CircuitBreaker`]
+    #[inline]
+    pub fn circuit_breaker(self, config: BreakerConfig) -> CircuitBreaker<A> {
+        CircuitBreaker::new(self, config)
     }
 
     /// 升级到远程地址
@@ -49,11 +124,38 @@ where
         A: MessageHandler<M>,
     {
         let (envelope, rx) = envelope::pack(msg);
+        if !self.intake.try_enter() {
+            return Err(ChannelSendError::RateLimited(envelope));
+        }
         self.sender
             .send(envelope)
             .await
             .map_err::<ChannelSendError<Envelope<A>>, _>(Into::into)?;
-        Ok(ResponseHandle(rx))
+        Ok(ResponseHandle(rx.into()))
+    }
+
+    /// 附带元数据发送, 见[`EnvelopeMeta`]
+    ///
+    /// 元数据的发送时间会更新为当前时间.
+    #[inline]
+    pub async fn send_with<M>(
+        &self,
+        msg: M,
+        meta: EnvelopeMeta,
+    ) -> Result<ResponseHandle<<A as MessageHandler<M>>::Output>, ChannelSendError<Envelope<A>>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        let (envelope, rx) = envelope::pack(msg);
+        if !self.intake.try_enter() {
+            return Err(ChannelSendError::RateLimited(envelope));
+        }
+        self.sender
+            .send(envelope.with_meta(Some(meta.stamp())))
+            .await
+            .map_err::<ChannelSendError<Envelope<A>>, _>(Into::into)?;
+        Ok(ResponseHandle(rx.into()))
     }
 
     #[inline]
@@ -66,10 +168,42 @@ where
         A: MessageHandler<M>,
     {
         let (envelope, rx) = envelope::pack(msg);
+        if !self.intake.try_enter() {
+            return Err(ChannelTrySendError::RateLimited(envelope));
+        }
         self.sender
             .try_send(envelope)
             .map_err::<ChannelTrySendError<Envelope<A>>, _>(Into::into)?;
-        Ok(ResponseHandle(rx))
+        Ok(ResponseHandle(rx.into()))
+    }
+
+    /// 发送不需要响应的消息
+    ///
+    /// 不创建响应通道, 处理结果被丢弃.
+    #[inline]
+    pub async fn do_send<M>(&self, msg: M) -> Result<(), ChannelSendError<Envelope<A>>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        let envelope = envelope::pack_detached(msg);
+        if !self.intake.try_enter() {
+            return Err(ChannelSendError::RateLimited(envelope));
+        }
+        self.sender.send(envelope).await.map_err(Into::into)
+    }
+
+    #[inline]
+    pub fn try_do_send<M>(&self, msg: M) -> Result<(), ChannelTrySendError<Envelope<A>>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        let envelope = envelope::pack_detached(msg);
+        if !self.intake.try_enter() {
+            return Err(ChannelTrySendError::RateLimited(envelope));
+        }
+        self.sender.try_send(envelope).map_err(Into::into)
     }
 
     /// send + recv
@@ -81,12 +215,71 @@ where
     {
         Ok(self.send(msg).await?.recv().await?)
     }
+
+    /// try_send + recv
+    #[inline]
+    pub async fn try_call<M>(
+        &self,
+        msg: M,
+    ) -> Result<<A as MessageHandler<M>>::Output, CallError<A>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        Ok(self.try_send(msg)?.recv().await?)
+    }
+
+    /// 只能发送`M`的地址, 见[`Recipient`]
+    #[inline]
+    pub fn recipient<M>(&self) -> Recipient<M>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        self.clone().into()
+    }
+
+    /// 只能发送`M`并接收`M::Result`的地址, 见[`Caller`]
+    #[inline]
+    pub fn caller<M>(&self) -> Caller<M>
+    where
+        M: TypedMessage + 'static,
+        A: MessageHandler<M, Output = M::Result>,
+    {
+        self.clone().into()
+    }
+
+    /// 失败时按照`policy`重试[`LocalAddress::try_call`], 返回最后一次的错误
+    ///
+    /// 每次调用都会clone一次消息, 最后一次调用使用原消息.
+    pub async fn call_with_retry<M>(
+        &self,
+        msg: M,
+        policy: &RetryPolicy,
+    ) -> Result<<A as MessageHandler<M>>::Output, CallError<A>>
+    where
+        M: Message + Clone + 'static,
+        A: MessageHandler<M>,
+    {
+        for retry in 1..policy.max_attempts() {
+            match self.try_call(msg.clone()).await {
+                Ok(output) => return Ok(output),
+                Err(err) if policy.is_retryable(err.kind()) => {
+                    log::debug!("call failed: {}, retry after backoff.", err);
+                    crate::rt::sleep(policy.delay(retry)).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        self.try_call(msg).await
+    }
 }
 
-impl<A> Clone for LocalAddress<A> where A: Actor {
+impl<A> Clone for LocalAddress<A> where A: UnsendActor {
     fn clone(&self) -> Self {
         LocalAddress {
             sender: self.sender.clone(),
+            intake: Arc::clone(&self.intake),
         }
     }
 }
@@ -94,15 +287,37 @@ impl<A> Clone for LocalAddress<A> where A: Actor {
 #[derive(Error)]
 pub enum CallError<A>
 where
-    A: Actor + 'static,
+    A: UnsendActor,
 {
     #[error("send error: {0}")]
     SendError(#[from] ChannelSendError<Envelope<A>>),
+    #[error("try send error: {0}")]
+    TrySendError(#[from] ChannelTrySendError<Envelope<A>>),
     #[error("recv error: {0}")]
     RecvError(#[from] HandlerPanic),
 }
 
-impl<A> Debug for CallError<A> where A: Actor {
+impl<A> CallError<A>
+where
+    A: UnsendActor,
+{
+    pub fn kind(&self) -> CallErrorKind {
+        match self {
+            CallError::SendError(ChannelSendError::Disconnected(_))
+            | CallError::TrySendError(ChannelTrySendError::Disconnected(_)) => {
+                CallErrorKind::Disconnected
+            }
+            CallError::SendError(ChannelSendError::RateLimited(_))
+            | CallError::TrySendError(ChannelTrySendError::RateLimited(_)) => {
+                CallErrorKind::RateLimited
+            }
+            CallError::TrySendError(ChannelTrySendError::Full(_)) => CallErrorKind::Full,
+            CallError::RecvError(_) => CallErrorKind::HandlerPanic,
+        }
+    }
+}
+
+impl<A> Debug for CallError<A> where A: UnsendActor {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "CallError")
     }
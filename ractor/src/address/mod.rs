@@ -1,4 +1,4 @@
-pub use local::LocalAddress;
+pub use local::{CallError, LocalAddress};
 #[cfg(feature = "remote")]
 pub use remote::RemoteAddress;
 
@@ -8,7 +8,7 @@ use url::Url;
 use ractor_rpc::{RemoteType, RpcClient, RpcServer};
 
 use crate::address::LocalAddress;
-use crate::message::Message;
+use crate::message::{Message, TypedMessage};
 use crate::{Actor, MessageHandler, ResponseHandle};
 
 #[derive(Debug, Error)]
@@ -49,7 +49,22 @@ impl RemoteAddress {
     {
         let rx = self.client.send(ractor_rpc::Message::new(msg)).await?;
 
-        Ok(ResponseHandle(rx))
+        Ok(ResponseHandle(rx.into()))
+    }
+
+    /// 发送声明了响应类型的消息, 不需要知道actor的类型, 见[`TypedMessage`]
+    #[inline]
+    pub async fn send_typed<M>(
+        &mut self,
+        msg: M,
+    ) -> Result<ResponseHandle<M::Result>, RemoteAddressError>
+    where
+        M: TypedMessage + RemoteType,
+        M::Result: RemoteType,
+    {
+        let rx = self.client.send(ractor_rpc::Message::new(msg)).await?;
+
+        Ok(ResponseHandle(rx.into()))
     }
 }
 
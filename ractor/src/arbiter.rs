@@ -0,0 +1,165 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle as ThreadHandle;
+
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::rt::Spawner;
+
+static ARBITER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 在独立线程上运行的单线程runtime
+///
+/// 对延迟敏感的actor可以通过[`Broker::spawn_on`](crate::Broker::spawn_on)生成在专用的arbiter上,
+/// 不和其他任务共享tokio的工作线程. 这些actor的[`GlobalContext::spawn`](crate::GlobalContext::spawn)和
+/// [`Context::defer`](crate::Context::defer)同样在arbiter上执行.
+///
+/// 丢弃`Arbiter`时会停止runtime, 其上的actor也随之结束.
+pub struct Arbiter {
+    id: usize,
+    handle: Handle,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<ThreadHandle<()>>,
+}
+
+impl Arbiter {
+    #[inline]
+    pub fn new() -> Self {
+        Arbiter::start(None)
+    }
+
+    /// 把arbiter的线程绑定到指定的CPU核心上
+    ///
+    /// 需要`affinity` feature, 目前只支持Linux, 其他平台上会被忽略.
+    #[cfg(feature = "affinity")]
+    #[inline]
+    pub fn pinned(core: usize) -> Self {
+        Arbiter::start(Some(core))
+    }
+
+    /// 每个CPU核心一个arbiter
+    ///
+    /// 启用`affinity` feature时, 第`i`个arbiter绑定到第`i`个核心.
+    pub fn thread_per_core() -> Vec<Arbiter> {
+        let cores = std::thread::available_parallelism()
+            .map(usize::from)
+            .unwrap_or(1);
+        (0..cores)
+            .map(|core| {
+                if cfg!(feature = "affinity") {
+                    Arbiter::start(Some(core))
+                } else {
+                    Arbiter::new()
+                }
+            })
+            .collect()
+    }
+
+    fn start(core: Option<usize>) -> Self {
+        let id = ARBITER_ID.fetch_add(1, Ordering::Relaxed);
+        let (handle_tx, handle_rx) = std::sync::mpsc::channel();
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+        let thread = std::thread::Builder::new()
+            .name(format!("ractor-arbiter-{}", id))
+            .spawn(move || {
+                if let Some(core) = core {
+                    pin_to_core(core);
+                }
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("failed to build the runtime of the arbiter");
+                handle_tx.send(rt.handle().clone()).ok();
+                rt.block_on(stop_rx).ok();
+            })
+            .expect("failed to spawn the thread of the arbiter");
+
+        Arbiter {
+            id,
+            handle: handle_rx
+                .recv()
+                .expect("the thread of the arbiter exited unexpectedly"),
+            stop: Some(stop_tx),
+            thread: Some(thread),
+        }
+    }
+
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    #[inline]
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// 在arbiter上执行任务
+    #[inline]
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.handle.spawn(future)
+    }
+
+    #[inline]
+    pub(crate) fn spawner(&self) -> Spawner {
+        Spawner::Handle(self.handle.clone())
+    }
+
+    /// 停止runtime并等待线程结束
+    ///
+    /// 不能在arbiter自己的线程上调用.
+    pub fn stop(mut self) {
+        self.shutdown(true);
+    }
+
+    fn shutdown(&mut self, join: bool) {
+        if let Some(stop) = self.stop.take() {
+            stop.send(()).ok();
+        }
+        if let Some(thread) = self.thread.take() {
+            if join && thread.thread().id() != std::thread::current().id() {
+                thread.join().ok();
+            }
+        }
+    }
+}
+
+impl Default for Arbiter {
+    #[inline]
+    fn default() -> Self {
+        Arbiter::new()
+    }
+}
+
+impl Drop for Arbiter {
+    fn drop(&mut self) {
+        self.shutdown(false);
+    }
+}
+
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+fn pin_to_core(core: usize) {
+    // SAFETY: `cpu_set_t`是普通的位图, 全零是合法的值
+    let ok = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core % libc::CPU_SETSIZE as usize, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    };
+    if !ok {
+        log::warn!(
+            "failed to pin the arbiter to core {}: {}",
+            core,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(all(feature = "affinity", target_os = "linux")))]
+fn pin_to_core(_core: usize) {}
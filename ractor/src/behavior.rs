@@ -0,0 +1,69 @@
+use crate::Actor;
+
+/// 行为, 即有限状态机中的一个状态
+///
+/// 通常在无字段的枚举上使用`#[derive(Behavior)]`实现.
+pub trait Behavior: Copy + Eq + Send + 'static {
+    fn index(self) -> usize;
+
+    fn from_index(index: usize) -> Self;
+
+    fn name(self) -> &'static str;
+}
+
+/// 可以在运行时切换行为的actor
+///
+/// 使用[`Context::become`]/[`Context::unbecome`]切换行为,
+/// 在`impl MessageHandler<M>`上使用`#[behavior(...)]`声明在哪些行为下处理消息`M`.
+///
+/// ```ignore
+/// #[derive(Clone, Copy, PartialEq, Eq, Behavior)]
+/// enum Phase {
+///     Connecting,
+///     Authenticated,
+/// }
+///
+/// #[behavior(Phase::Authenticated, unhandled = reply(Err(NotReady)))]
+/// impl MessageHandler<Query> for Conn {
+///     type Output = Result<Rows, NotReady>;
+///     // ...
+/// }
+/// ```
+pub trait BehaviorActor: Actor {
+    type Behavior: Behavior;
+
+    /// 初始行为, 重置之后也会回到此行为
+    const INITIAL: Self::Behavior;
+
+    /// 当前行为下不处理的消息的默认去向
+    const UNHANDLED: Fallback = Fallback::DeadLetter;
+}
+
+/// 当前行为不处理消息时的处理方式
+pub enum Unhandled<O> {
+    /// 暂存消息, 见[`Context::stash`]
+    ///
+    /// 暂存区已满时视为[`Unhandled::DeadLetter`]
+    Stash,
+    /// 丢弃消息并记录日志, 发送者会收到[`HandlerPanic`]
+    DeadLetter,
+    /// 不处理消息, 直接回复
+    Reply(O),
+}
+
+/// [`BehaviorActor::UNHANDLED`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fallback {
+    Stash,
+    DeadLetter,
+}
+
+impl<O> From<Fallback> for Unhandled<O> {
+    #[inline]
+    fn from(fallback: Fallback) -> Self {
+        match fallback {
+            Fallback::Stash => Unhandled::Stash,
+            Fallback::DeadLetter => Unhandled::DeadLetter,
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::address::CallError;
+use crate::message::Message;
+use crate::{Actor, LocalAddress, MessageHandler};
+
+/// 熔断器的状态
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BreakerState {
+    /// 正常调用, 统计最近的失败率
+    Closed,
+    /// 直接拒绝调用, 直到`open_duration`之后进入[`BreakerState::HalfOpen`]
+    Open,
+    /// 只放行少量的探测调用, 全部成功则关闭, 任何一个失败则重新打开
+    HalfOpen,
+}
+
+/// 熔断器状态改变的事件
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BreakerEvent {
+    pub from: BreakerState,
+    pub to: BreakerState,
+}
+
+/// 熔断器的配置
+///
+/// ```ignore
+/// // 最近20次调用中失败超过一半时熔断, 超过100ms的调用也算作失败
+/// let config = BreakerConfig::default()
+///     .with_failure_rate(0.5, 10)
+///     .with_window(20)
+///     .with_slow_call(Duration::from_millis(100));
+/// ```
+#[derive(Clone, Debug)]
+pub struct BreakerConfig {
+    failure_rate: f64,
+    min_calls: usize,
+    window: usize,
+    slow_call: Option<Duration>,
+    timeout: Option<Duration>,
+    open_duration: Duration,
+    half_open_calls: u32,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            failure_rate: 0.5,
+            min_calls: 10,
+            window: 100,
+            slow_call: None,
+            timeout: None,
+            open_duration: Duration::from_secs(10),
+            half_open_calls: 1,
+        }
+    }
+}
+
+impl BreakerConfig {
+    /// 至少有`min_calls`次调用, 且失败率不低于`rate`时熔断
+    pub fn with_failure_rate(mut self, rate: f64, min_calls: usize) -> Self {
+        self.failure_rate = rate.clamp(0.0, 1.0);
+        self.min_calls = min_calls.max(1);
+        self
+    }
+
+    /// 统计失败率的最近调用次数
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// 耗时不低于`dur`的调用算作失败
+    pub fn with_slow_call(mut self, dur: Duration) -> Self {
+        self.slow_call = Some(dur);
+        self
+    }
+
+    /// 调用超时, 超时算作失败
+    pub fn with_timeout(mut self, dur: Duration) -> Self {
+        self.timeout = Some(dur);
+        self
+    }
+
+    /// 熔断之后多久进入半开状态
+    pub fn with_open_duration(mut self, dur: Duration) -> Self {
+        self.open_duration = dur;
+        self
+    }
+
+    /// 半开状态下的探测调用数量
+    pub fn with_half_open_calls(mut self, calls: u32) -> Self {
+        self.half_open_calls = calls.max(1);
+        self
+    }
+}
+
+/// 熔断器包装的地址
+///
+/// actor持续panic或超时的时候快速失败, 避免调用者堆积在[`LocalAddress::call`]上.
+/// 所有clone共享同一个熔断器.
+pub struct CircuitBreaker<A>
+where
+    A: Actor,
+{
+    addr: LocalAddress<A>,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    config: BreakerConfig,
+    inner: Mutex<Inner>,
+    events: broadcast::Sender<BreakerEvent>,
+}
+
+struct Inner {
+    state: BreakerState,
+    /// 最近调用的结果, `true`表示失败
+    window: VecDeque<bool>,
+    failures: usize,
+    opened_at: Instant,
+    /// 半开状态下正在进行的探测
+    probing: u32,
+    /// 半开状态下成功的探测
+    succeeded: u32,
+    /// 每次改变状态时递增, 用于忽略过时的调用结果
+    generation: u64,
+}
+
+impl<A> CircuitBreaker<A>
+where
+    A: Actor,
+{
+    pub fn new(addr: LocalAddress<A>, config: BreakerConfig) -> Self {
+        let (events, _) = broadcast::channel(16);
+        CircuitBreaker {
+            addr,
+            shared: Arc::new(Shared {
+                inner: Mutex::new(Inner {
+                    state: BreakerState::Closed,
+                    window: VecDeque::with_capacity(config.window),
+                    failures: 0,
+                    opened_at: Instant::now(),
+                    probing: 0,
+                    succeeded: 0,
+                    generation: 0,
+                }),
+                config,
+                events,
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn addr(&self) -> &LocalAddress<A> {
+        &self.addr
+    }
+
+    /// 当前的状态
+    ///
+    /// 熔断超过`open_duration`之后, 在下一次调用时才会进入半开状态.
+    #[inline]
+    pub fn state(&self) -> BreakerState {
+        self.shared.inner.lock().unwrap().state
+    }
+
+    /// 订阅状态改变的事件
+    #[inline]
+    pub fn subscribe(&self) -> broadcast::Receiver<BreakerEvent> {
+        self.shared.events.subscribe()
+    }
+
+    /// 熔断时直接返回[`BreakerError::Open`]
+    pub async fn call<M>(
+        &self,
+        msg: M,
+    ) -> Result<<A as MessageHandler<M>>::Output, BreakerError<M, A>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        let mut permit = match self.shared.acquire() {
+            Some(permit) => permit,
+            None => return Err(BreakerError::Open(msg)),
+        };
+
+        let start = Instant::now();
+        let config = &self.shared.config;
+        let result = match config.timeout {
+            Some(timeout) => match crate::rt::timeout(timeout, self.addr.call(msg)).await {
+                Ok(result) => result.map_err(BreakerError::Call),
+                Err(_) => Err(BreakerError::Timeout),
+            },
+            None => self.addr.call(msg).await.map_err(BreakerError::Call),
+        };
+        let slow = config.slow_call.is_some_and(|slow| start.elapsed() >= slow);
+        permit.record(result.is_err() || slow);
+        result
+    }
+}
+
+impl<A> Clone for CircuitBreaker<A>
+where
+    A: Actor,
+{
+    fn clone(&self) -> Self {
+        CircuitBreaker {
+            addr: self.addr.clone(),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Shared {
+    fn acquire(&self) -> Option<Permit<'_>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::Open
+            && inner.opened_at.elapsed() >= self.config.open_duration
+        {
+            inner.probing = 0;
+            inner.succeeded = 0;
+            self.transition(&mut inner, BreakerState::HalfOpen);
+        }
+        match inner.state {
+            BreakerState::Closed => {}
+            BreakerState::Open => return None,
+            BreakerState::HalfOpen => {
+                if inner.probing + inner.succeeded >= self.config.half_open_calls {
+                    return None;
+                }
+                inner.probing += 1;
+            }
+        }
+        Some(Permit {
+            shared: self,
+            generation: inner.generation,
+            done: false,
+        })
+    }
+
+    fn record(&self, generation: u64, failed: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        // 状态已经改变, 这次调用的结果已经过时了
+        if inner.generation != generation {
+            return;
+        }
+        match inner.state {
+            BreakerState::Closed => {
+                inner.window.push_back(failed);
+                if failed {
+                    inner.failures += 1;
+                }
+                if inner.window.len() > self.config.window && inner.window.pop_front() == Some(true)
+                {
+                    inner.failures -= 1;
+                }
+                let calls = inner.window.len();
+                // 没有失败时不打开, 否则`failure_rate`为0时总是打开
+                if calls >= self.config.min_calls
+                    && inner.failures > 0
+                    && inner.failures as f64 >= self.config.failure_rate * calls as f64
+                {
+                    self.open(&mut inner);
+                }
+            }
+            BreakerState::HalfOpen => {
+                inner.probing -= 1;
+                if failed {
+                    self.open(&mut inner);
+                } else {
+                    inner.succeeded += 1;
+                    if inner.succeeded >= self.config.half_open_calls {
+                        inner.window.clear();
+                        inner.failures = 0;
+                        self.transition(&mut inner, BreakerState::Closed);
+                    }
+                }
+            }
+            BreakerState::Open => {}
+        }
+    }
+
+    /// 取消的探测不计入结果
+    fn release(&self, generation: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation == generation && inner.state == BreakerState::HalfOpen {
+            inner.probing -= 1;
+        }
+    }
+
+    fn open(&self, inner: &mut Inner) {
+        inner.opened_at = Instant::now();
+        self.transition(inner, BreakerState::Open);
+    }
+
+    fn transition(&self, inner: &mut Inner, to: BreakerState) {
+        let from = std::mem::replace(&mut inner.state, to);
+        if from != to {
+            inner.generation += 1;
+            log::debug!("circuit breaker: {:?} -> {:?}", from, to);
+            // 没有订阅者时发送失败
+            self.events.send(BreakerEvent { from, to }).ok();
+        }
+    }
+}
+
+/// 一次被放行的调用
+struct Permit<'a> {
+    shared: &'a Shared,
+    generation: u64,
+    done: bool,
+}
+
+impl Permit<'_> {
+    fn record(&mut self, failed: bool) {
+        self.done = true;
+        self.shared.record(self.generation, failed);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.shared.release(self.generation);
+        }
+    }
+}
+
+pub enum BreakerError<M, A>
+where
+    A: Actor + 'static,
+{
+    /// 熔断中, 返回原消息
+    Open(M),
+    Timeout,
+    Call(CallError<A>),
+}
+
+impl<M, A> Debug for BreakerError<M, A>
+where
+    A: Actor,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakerError::Open(_) => write!(f, "The circuit breaker is open."),
+            BreakerError::Timeout => write!(f, "The call timed out."),
+            BreakerError::Call(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<M, A> Display for BreakerError<M, A>
+where
+    A: Actor,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<M, A> std::error::Error for BreakerError<M, A> where A: Actor {}
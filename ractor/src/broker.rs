@@ -4,11 +4,18 @@ use std::sync::Arc;
 
 use crossfire::mpmc::bounded_future_both;
 use futures::future::join_all;
-use tokio::task::JoinHandle;
+use futures::stream::{FuturesUnordered, Stream};
 
-use crate::actor::Actor;
-use crate::actor_runner::ActorRunner;
-use crate::context::{GlobalContext, Inner, State};
+use crate::actor::{Actor, UnsendActor};
+use crate::actor::panic_message;
+use crate::actor_runner::{ActorExit, ActorRunner};
+#[cfg(feature = "rt-tokio")]
+use crate::arbiter::Arbiter;
+use crate::context::{GlobalContext, Inner};
+#[cfg(feature = "introspect")]
+use crate::introspect;
+use crate::introspect::{ActorStatus, Monitor};
+use crate::rt::{JoinHandle, Placement};
 use crate::{Context, LocalAddress};
 
 pub struct Broker<A>
@@ -16,7 +23,10 @@ where
     A: Actor,
 {
     addr: Arc<LocalAddress<A>>,
-    actor_runner_handles: Vec<JoinHandle<()>>,
+    actor_runner_handles: Vec<SpawnHandle<A>>,
+    /// actor全部结束之后, broker还在时依然出现在快照中
+    #[cfg(feature = "introspect")]
+    _registration: introspect::Registration,
 }
 
 impl<A> Broker<A>
@@ -43,24 +53,12 @@ where
     ///
     /// 但在普通情况下关闭`并发生成`效率更好
     pub async fn spawn_with_args(quantity: usize, concurrent_spawn: bool, args: A::Args) -> Self {
-        let (tx, rx) = bounded_future_both(A::MAIL_BOX_SIZE as usize);
-        let addr = Arc::new(LocalAddress::new(tx));
-
-        let global_context = GlobalContext {
-            inner: Arc::new(Inner {
-                self_addr: Arc::downgrade(&addr),
-                recipient: rx,
-                create_args: args
-            }),
-        };
+        let (addr, global_context) = new_global_context(args, Placement::default());
 
         let join_handles = if concurrent_spawn {
             join_all(
                 (0..quantity)
-                    .map(|_| Context {
-                        global_context: global_context.clone(),
-                        state: State::Continue,
-                    })
+                    .map(|_| Context::new(global_context.clone()))
                     .map(|mut ctx| async move {
                         let actor = A::create(&mut ctx).await;
                         (actor, ctx)
@@ -68,17 +66,14 @@ where
             )
             .await
             .into_iter()
-            .map(|(actor, context)| tokio::spawn(ActorRunner { actor, context }.run()))
-            .collect::<Vec<JoinHandle<()>>>()
+            .map(|(actor, context)| SpawnHandle::spawn(actor, context))
+            .collect::<Vec<SpawnHandle<A>>>()
         } else {
             let mut join_handles = Vec::with_capacity(quantity);
             for _ in 0..quantity {
-                let mut context = Context {
-                    global_context: global_context.clone(),
-                    state: State::Continue,
-                };
+                let mut context = Context::new(global_context.clone());
                 let actor = A::create(&mut context).await;
-                join_handles.push(tokio::spawn(ActorRunner { actor, context }.run()))
+                join_handles.push(SpawnHandle::spawn(actor, context))
             }
             join_handles
         };
@@ -86,13 +81,54 @@ where
         Broker {
             addr,
             actor_runner_handles: join_handles,
+            #[cfg(feature = "introspect")]
+            _registration: Arc::clone(&global_context.registration),
+        }
+    }
+
+    /// 在`arbiter`上生成`quantity`个actor
+    ///
+    /// [`Actor::create`]也在`arbiter`上执行.
+    #[cfg(feature = "rt-tokio")]
+    #[inline]
+    pub fn spawn_on(arbiter: &Arbiter, quantity: usize, args: A::Args) -> Self {
+        Broker::spawn_sharded(std::slice::from_ref(arbiter), quantity, args)
+    }
+
+    /// 在每个arbiter上生成`per_arbiter`个actor, 共享同一个信箱
+    ///
+    /// 配合[`Arbiter::thread_per_core`]使用时, 每个核心运行broker的一部分actor.
+    #[cfg(feature = "rt-tokio")]
+    pub fn spawn_sharded(arbiters: &[Arbiter], per_arbiter: usize, args: A::Args) -> Self {
+        let placement = Placement::new(arbiters.iter().map(Arbiter::spawner).collect());
+        let (addr, global_context) = new_global_context(args, placement);
+
+        // `Context::new`依次分配arbiter
+        let actor_runner_handles = (0..arbiters.len() * per_arbiter)
+            .map(|_| {
+                let mut context = Context::new(global_context.clone());
+                let status = Arc::clone(&context.status);
+                let spawner = context.spawner.clone();
+                let runner = async move {
+                    let actor = A::create(&mut context).await;
+                    ActorRunner::new(actor, context).run().await
+                };
+                SpawnHandle::from_parts(spawner.spawn(runner), status)
+            })
+            .collect();
+
+        Broker {
+            addr,
+            actor_runner_handles,
+            #[cfg(feature = "introspect")]
+            _registration: Arc::clone(&global_context.registration),
         }
     }
 
     /// 将[`GlobalContext::spawn`]产生的Actor绑定到Broker
     #[inline]
     pub fn bind(&mut self, handle: SpawnHandle<A>) {
-        self.actor_runner_handles.push(handle.join_handle)
+        self.actor_runner_handles.push(handle)
     }
 }
 
@@ -105,9 +141,23 @@ where
         &self.addr
     }
 
-    pub async fn wait_for_actors(self) {
+    /// 等待所有actor结束, 按照生成的顺序返回每个actor的[`ActorExit`]
+    ///
+    /// 如果被[`LocalAddress::pause`]暂停, 会先恢复.
+    pub async fn wait_for_actors(self) -> Vec<ActorExit> {
+        self.addr.resume();
         drop(self.addr);
-        join_all(self.actor_runner_handles).await;
+        join_all(self.actor_runner_handles.into_iter().map(SpawnHandle::join)).await
+    }
+
+    /// 同[`Broker::wait_for_actors`], 按照结束的顺序产生[`ActorExit`]
+    pub fn exits(self) -> impl Stream<Item = ActorExit> {
+        self.addr.resume();
+        drop(self.addr);
+        self.actor_runner_handles
+            .into_iter()
+            .map(SpawnHandle::join)
+            .collect::<FuturesUnordered<_>>()
     }
 
     pub fn abort(&self) {
@@ -128,26 +178,175 @@ where
     }
 }
 
-/// 实际上等同于[`JoinHandle<()>`]
+/// 创建信箱和共享的上下文
+pub(crate) fn new_global_context<A>(
+    args: A::Args,
+    placement: Placement,
+) -> (Arc<LocalAddress<A>>, GlobalContext<A>)
+where
+    A: UnsendActor,
+{
+    let (tx, rx) = bounded_future_both(A::MAIL_BOX_SIZE as usize);
+    let addr = Arc::new(LocalAddress::new(tx));
+
+    let monitor = Arc::new(Monitor::default());
+    let global_context = GlobalContext {
+        inner: Arc::new_cyclic(|_inner| Inner {
+            self_addr: Arc::downgrade(&addr),
+            recipient: rx,
+            intake: Arc::clone(&addr.intake),
+            #[cfg(feature = "introspect")]
+            registration: introspect::register(
+                _inner.clone(),
+                Arc::clone(&monitor),
+                Arc::clone(&addr.intake),
+            ),
+            monitor,
+            placement,
+            create_args: args,
+        }),
+    };
+    (addr, global_context)
+}
+
+/// 单个actor的[`JoinHandle`]
 ///
-/// 为了保证`<A>`是相同的
+/// 带有`<A>`, 保证只能绑定到相同类型的Broker上
+///
+/// 也可以从任务的句柄转换得到, 此时任务被停止或panic后[`ActorExit`]中的编号和重启次数为0.
 pub struct SpawnHandle<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
-    join_handle: JoinHandle<()>,
+    join_handle: Join,
+    status: Option<Arc<ActorStatus>>,
     marker: PhantomData<A>,
 }
 
-impl<A> From<JoinHandle<()>> for SpawnHandle<A>
+enum Join {
+    Ractor(JoinHandle<ActorExit>),
+    #[cfg(feature = "rt-tokio")]
+    Tokio(tokio::task::JoinHandle<ActorExit>),
+    /// 专用线程上的actor, 线程结束时发送结果
+    #[cfg(feature = "rt-tokio")]
+    Thread(tokio::sync::oneshot::Receiver<std::thread::Result<ActorExit>>),
+}
+
+impl<A> SpawnHandle<A>
 where
     A: Actor,
+{
+    /// 在`context`分配的位置上运行actor
+    pub(crate) fn spawn(actor: A, context: Context<A>) -> Self {
+        let status = Arc::clone(&context.status);
+        let spawner = context.spawner.clone();
+        SpawnHandle::from_parts(spawner.spawn(ActorRunner::new(actor, context).run()), status)
+    }
+}
+
+impl<A> SpawnHandle<A>
+where
+    A: UnsendActor,
 {
     #[inline]
-    fn from(h: JoinHandle<()>) -> Self {
+    pub(crate) fn from_parts(join_handle: JoinHandle<ActorExit>, status: Arc<ActorStatus>) -> Self {
+        SpawnHandle {
+            join_handle: Join::Ractor(join_handle),
+            status: Some(status),
+            marker: PhantomData,
+        }
+    }
+
+    /// 在专用线程上运行的actor, 见[`SyncBroker`](crate::SyncBroker)
+    #[cfg(feature = "rt-tokio")]
+    #[inline]
+    pub(crate) fn from_thread(
+        rx: tokio::sync::oneshot::Receiver<std::thread::Result<ActorExit>>,
+        status: Arc<ActorStatus>,
+    ) -> Self {
+        SpawnHandle {
+            join_handle: Join::Thread(rx),
+            status: Some(status),
+            marker: PhantomData,
+        }
+    }
+
+    /// `spawn_local`生成的actor
+    #[cfg(feature = "rt-tokio")]
+    #[inline]
+    pub(crate) fn from_local(
+        join_handle: tokio::task::JoinHandle<ActorExit>,
+        status: Arc<ActorStatus>,
+    ) -> Self {
+        SpawnHandle {
+            join_handle: Join::Tokio(join_handle),
+            status: Some(status),
+            marker: PhantomData,
+        }
+    }
+
+    /// 等待actor结束
+    pub async fn join(self) -> ActorExit {
+        let panic = match self.join_handle {
+            Join::Ractor(handle) => match handle.await {
+                Ok(exit) => return exit,
+                Err(err) => err.into_panic(),
+            },
+            #[cfg(feature = "rt-tokio")]
+            Join::Tokio(handle) => match handle.await {
+                Ok(exit) => return exit,
+                Err(err) => err.try_into_panic().ok(),
+            },
+            #[cfg(feature = "rt-tokio")]
+            Join::Thread(rx) => match rx.await {
+                Ok(Ok(exit)) => return exit,
+                Ok(Err(panic)) => Some(panic),
+                Err(_) => None,
+            },
+        };
+        ActorExit::interrupted(
+            self.status.as_deref(),
+            panic.map(|panic| panic_message(&*panic).unwrap_or_default()),
+        )
+    }
+
+    /// 停止actor的任务, 对[`SyncBroker`](crate::SyncBroker)的专用线程无效
+    #[inline]
+    pub fn abort(&self) {
+        match &self.join_handle {
+            Join::Ractor(handle) => handle.abort(),
+            #[cfg(feature = "rt-tokio")]
+            Join::Tokio(handle) => handle.abort(),
+            // 线程不能被中止
+            #[cfg(feature = "rt-tokio")]
+            Join::Thread(_) => {}
+        }
+    }
+}
+
+impl<A> From<JoinHandle<ActorExit>> for SpawnHandle<A>
+where
+    A: UnsendActor,
+{
+    fn from(join_handle: JoinHandle<ActorExit>) -> Self {
+        SpawnHandle {
+            join_handle: Join::Ractor(join_handle),
+            status: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "rt-tokio")]
+impl<A> From<tokio::task::JoinHandle<ActorExit>> for SpawnHandle<A>
+where
+    A: UnsendActor,
+{
+    fn from(join_handle: tokio::task::JoinHandle<ActorExit>) -> Self {
         SpawnHandle {
-            join_handle: h,
-            marker: PhantomData::default(),
+            join_handle: Join::Tokio(join_handle),
+            status: None,
+            marker: PhantomData,
         }
     }
 }
@@ -3,16 +3,28 @@ use std::ops::Deref;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 
+use futures::future::BoxFuture;
+use futures::Future;
 #[cfg(feature = "remote")]
-use futures::{Future, FutureExt};
+use futures::FutureExt;
 use tokio::sync::Notify;
 
 #[cfg(feature = "remote")]
 use ractor_rpc::{deserialize, serialize, RemoteType};
 
-use crate::actor_runner::ActorRunner;
+use crate::behavior::{Behavior, BehaviorActor};
 use crate::broker::SpawnHandle;
+use crate::deferred::Deferred;
 use crate::envelope::MailBoxRx;
+use crate::error::StashFull;
+use crate::message::{Message, MessageHandler};
+use crate::meta::EnvelopeMeta;
+use crate::intake::Intake;
+use crate::response::Cancellation;
+use crate::introspect::{ActorStatus, Monitor};
+use crate::rt::{Placement, Spawner};
+use crate::stash::Stash;
+use crate::actor::UnsendActor;
 use crate::{Actor, LocalAddress};
 
 /// 指示Actor之后的状态
@@ -39,7 +51,7 @@ pub enum State {
     Stop,
     /// 收到`Notify`的通知后恢复
     Pause(Arc<Notify>),
-    /// 调用[`tokio::task::yield_now`]
+    /// 调用[`rt::yield_now`](crate::rt::yield_now)
     Yield,
     /// 重置Actor状态并重启, 重新开始Actor生命周期
     ///
@@ -70,21 +82,88 @@ impl State {
 }
 
 /// 单个actor的上下文
-pub struct Context<A: ?Sized> where A: Actor {
+pub struct Context<A: ?Sized> where A: UnsendActor {
     pub(crate) global_context: GlobalContext<A>,
     /// 指定本周期结束的状态
-    pub state: State
+    pub state: State,
+    pub(crate) stash: Stash<A>,
+    /// 行为栈, 为空时表示[`BehaviorActor::INITIAL`]
+    pub(crate) behaviors: Vec<usize>,
+    pub(crate) deferred: Deferred<A>,
+    pub(crate) status: Arc<ActorStatus>,
+    /// actor和[`Context::defer`]的任务所在的位置
+    pub(crate) spawner: Spawner,
+    /// 正在处理的消息的元数据
+    pub(crate) meta: Option<Box<EnvelopeMeta>>,
+    /// 正在处理的可取消消息的响应通道, 见[`MessageHandler::CANCELLABLE`]
+    pub(crate) cancellation: Option<Cancellation>,
+    /// 最后一个已持久化的事件序号
+    #[cfg(feature = "persistence")]
+    pub(crate) persisted_seq: u64,
 }
 
 impl<A> Context<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
+    #[inline]
+    pub(crate) fn new(global_context: GlobalContext<A>) -> Self {
+        Context {
+            status: global_context.monitor.add(),
+            spawner: global_context.placement.next(),
+            global_context,
+            state: State::Continue,
+            stash: Stash::new(A::STASH_CAPACITY),
+            behaviors: Vec::new(),
+            deferred: Deferred::new(),
+            meta: None,
+            cancellation: None,
+            #[cfg(feature = "persistence")]
+            persisted_seq: 0,
+        }
+    }
+
     #[inline]
     pub fn global(&self) -> &GlobalContext<A> {
         &self.global_context
     }
 
+    /// actor在所属[`Broker`](crate::Broker)中的编号
+    ///
+    /// 按生成的顺序从0开始, 重启之后不变. [`Broker::spawn`](crate::Broker::spawn)生成的actor每次都是`0..quantity`.
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.status.id()
+    }
+
+    /// 调用者是否已经丢弃了正在处理的消息的[`ResponseHandle`](crate::ResponseHandle)
+    ///
+    /// 只对[`MessageHandler::CANCELLABLE`]的消息有效, 其他情况总是`false`.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|cancellation| cancellation.is_closed())
+    }
+
+    /// 等待调用者丢弃正在处理的消息的[`ResponseHandle`](crate::ResponseHandle)
+    ///
+    /// 不可取消时永远不会完成, 一般和耗时的操作一起`select`.
+    pub async fn cancelled(&mut self) {
+        match &mut self.cancellation {
+            Some(cancellation) => futures::future::poll_fn(|cx| cancellation.poll_closed(cx)).await,
+            None => futures::future::pending().await,
+        }
+    }
+
+    /// 正在处理的消息附带的元数据
+    ///
+    /// 只在处理消息期间有效, 通过[`LocalAddress::send_with`]发送的消息才有.
+    #[inline]
+    pub fn envelope_meta(&self) -> Option<&EnvelopeMeta> {
+        self.meta.as_deref()
+    }
+
     #[inline]
     pub fn stop(&mut self) {
         self.state = State::Stop;
@@ -111,11 +190,177 @@ where
     pub fn sleep(&mut self, dur: Duration) {
         self.state = State::Sleep(dur.as_millis() as u64);
     }
+
+    /// 暂存一条暂时无法处理的消息, 直到[`Context::unstash_all`]
+    ///
+    /// 暂存的消息没有响应, 本次处理的返回值照常发送给调用者.
+    /// 要暂存正在处理的消息并由它稍后响应调用者, 使用[`Context::stash_current`].
+    ///
+    /// 暂存的消息沿用当前消息的[`EnvelopeMeta`].
+    /// actor停止, 或者重置时没有[`Actor::KEEP_STASH_ON_RESET`], 暂存的消息作为死信丢弃.
+    ///
+    /// 暂存区已满([`Actor::STASH_CAPACITY`])时返回原消息.
+    pub fn stash<M>(&mut self, msg: M) -> Result<(), StashFull<M>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        if self.stash.is_full() {
+            return Err(StashFull(msg));
+        }
+        self.stash.push(msg, self.meta.clone(), false);
+        Ok(())
+    }
+
+    /// 在[`MessageHandler::handle`]中暂存正在处理的消息`msg`
+    ///
+    /// 调用者的响应交由暂存的消息在之后处理时发送, 本次处理的返回值将被丢弃.
+    /// `msg`的类型和正在处理的消息不同时, 同[`Context::stash`].
+    ///
+    /// ```ignore
+    /// async fn handle(&mut self, msg: Query, ctx: &mut Context<Self>) -> Self::Output {
+    ///     if !self.ready {
+    ///         ctx.stash_current(msg).ok();
+    ///         return Default::default();
+    ///     }
+    ///     ...
+    /// }
+    /// ```
+    pub fn stash_current<M>(&mut self, msg: M) -> Result<(), StashFull<M>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        if self.stash.is_full() {
+            return Err(StashFull(msg));
+        }
+        self.stash.push(msg, self.meta.clone(), true);
+        Ok(())
+    }
+
+    /// 取出所有暂存的消息, 按照暂存的顺序先于信箱中的消息处理
+    ///
+    /// 在当前处理中暂存的消息不会被取出.
+    #[inline]
+    pub fn unstash_all(&mut self) {
+        self.stash.unstash_all();
+    }
+
+    /// 暂存区中的消息数量
+    #[inline]
+    pub fn stashed_count(&self) -> usize {
+        self.stash.len()
+    }
+
+    /// 在actor之外执行`fut`, 完成之后再持有actor执行`then`
+    ///
+    /// 在[`MessageHandler::handle`]中调用时, `then`的返回值作为正在处理的消息的响应,
+    /// 本次处理的返回值将被丢弃. 这样处理函数可以立即返回,
+    /// actor在`fut`执行期间继续处理信箱中的其他消息.
+    ///
+    /// ```ignore
+    /// type Output = String;
+    ///
+    /// async fn handle(&mut self, msg: Fetch, ctx: &mut Context<Self>) -> Self::Output {
+    ///     ctx.defer(fetch(msg.url), |actor, body, _ctx| {
+    ///         async move {
+    ///             actor.cache.push(body.clone());
+    ///             body
+    ///         }
+    ///         .boxed()
+    ///     });
+    ///     String::new()
+    /// }
+    /// ```
+    ///
+    /// 调用者和普通的消息一样接收响应:
+    ///
+    /// ```ignore
+    /// let body: String = addr.call(Fetch { url }).await?;
+    /// ```
+    ///
+    /// 一次处理中多次调用时, 只有最后一次接管响应. `T`和正在处理的消息的`Output`不同时,
+    /// 调用者收到本次处理的返回值, `then`的返回值被丢弃. `then`中可以再次调用`defer`, 响应继续向后传递.
+    ///
+    /// `fut`在单独的任务中执行, 不受正在处理的消息影响; `then`在actor取出下一个消息之前执行,
+    /// 执行时的[`Context::envelope_meta`]和调用`defer`时相同.
+    /// `fut`panic时调用者收到`HandlerPanic`, actor继续运行.
+    /// 重置或停止之后, 尚未完成的任务会被取消.
+    pub fn defer<F, C, T>(&mut self, fut: F, then: C)
+    where
+        A: Actor,
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+        C: for<'a> FnOnce(&'a mut A, F::Output, &'a mut Context<A>) -> BoxFuture<'a, T>
+            + Send
+            + 'static,
+        T: Send + 'static,
+    {
+        self.deferred.push(&self.spawner, fut, then, self.meta.clone(), false)
+    }
+
+    /// 同[`Context::defer`], 但在`then`执行完毕之前actor不会从信箱中取出新的消息
+    ///
+    /// 用于需要保持严格顺序的消息.
+    pub fn defer_ordered<F, C, T>(&mut self, fut: F, then: C)
+    where
+        A: Actor,
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+        C: for<'a> FnOnce(&'a mut A, F::Output, &'a mut Context<A>) -> BoxFuture<'a, T>
+            + Send
+            + 'static,
+        T: Send + 'static,
+    {
+        self.deferred.push(&self.spawner, fut, then, self.meta.clone(), true)
+    }
+}
+
+impl<A> Context<A>
+where
+    A: BehaviorActor,
+{
+    /// 当前行为
+    #[inline]
+    pub fn behavior(&self) -> A::Behavior {
+        self.behaviors
+            .last()
+            .map_or(A::INITIAL, |index| A::Behavior::from_index(*index))
+    }
+
+    /// 使用新的行为替换当前行为
+    #[inline]
+    pub fn r#become(&mut self, behavior: A::Behavior) {
+        self.behaviors.pop();
+        self.behaviors.push(behavior.index());
+    }
+
+    /// 切换到新的行为, 保留当前行为以便[`Context::unbecome`]返回
+    #[inline]
+    pub fn become_stacked(&mut self, behavior: A::Behavior) {
+        self.behaviors.push(behavior.index());
+    }
+
+    /// 返回上一个行为
+    #[inline]
+    pub fn unbecome(&mut self) {
+        self.behaviors.pop();
+    }
+}
+
+impl<A: ?Sized> Drop for Context<A>
+where
+    A: UnsendActor,
+{
+    fn drop(&mut self) {
+        // 任务被停止时也会丢弃上下文
+        self.global_context.inner.monitor.remove(&self.status);
+    }
 }
 
 impl<A> Deref for Context<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
     type Target = GlobalContext<A>;
 
@@ -126,20 +371,26 @@ where
 }
 
 /// 全局(同一个地址的全部actor)共享的上下文
-pub struct GlobalContext<A: ?Sized> where A: Actor {
+pub struct GlobalContext<A: ?Sized> where A: UnsendActor {
     pub(crate) inner: Arc<Inner<A>>,
 }
 
-pub struct Inner<A: ?Sized> where A: Actor {
+pub struct Inner<A: ?Sized> where A: UnsendActor {
     pub self_addr: Weak<LocalAddress<A>>,
     pub(crate) recipient: MailBoxRx<A>,
+    pub(crate) intake: Arc<Intake>,
+    pub(crate) monitor: Arc<Monitor>,
+    /// 新的actor生成在哪里
+    pub(crate) placement: Placement,
+    #[cfg(feature = "introspect")]
+    pub(crate) registration: crate::introspect::Registration,
     /// 创建参数
     pub create_args: A::Args,
 }
 
 impl<A> Inner<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
     pub fn addr_holders_count(&self) -> usize {
         self.self_addr.strong_count()
@@ -148,32 +399,42 @@ where
     pub fn pending_message_count(&self) -> usize {
         self.recipient.len()
     }
+
+    /// 是否被[`LocalAddress::pause`]暂停
+    pub fn is_paused(&self) -> bool {
+        self.intake.is_paused()
+    }
 }
 
 impl<A> GlobalContext<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
     pub fn alive_count(&self) -> usize {
         Arc::strong_count(&self.inner)
     }
+}
 
+impl<A> GlobalContext<A>
+where
+    A: Actor,
+{
     /// 在上下文中产生一个新的Actor
     ///
     /// 可以[`Broker::bind`]将[`SpawnHandle`]绑定到一个Broker上, 以便统一管理.
+    ///
+    /// 通过[`Broker::spawn_on`]或[`Broker::spawn_sharded`]生成的actor, 新的actor依次生成在这些arbiter上.
+    /// [`Actor::create`]在调用者的任务中执行.
     pub async fn spawn(&self) -> SpawnHandle<A> {
-        let mut context = Context {
-            global_context: self.clone(),
-            state: State::Continue,
-        };
+        let mut context = Context::new(self.clone());
         let actor = A::create(&mut context).await;
-        tokio::spawn(ActorRunner { actor, context }.run()).into()
+        SpawnHandle::spawn(actor, context)
     }
 }
 
 impl<A> Debug for GlobalContext<A>
 where
-    A: Actor,
+    A: UnsendActor,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Actor GlobalContext")?;
@@ -191,11 +452,16 @@ where
             f,
             "There are {} holders with email addresses",
             self.addr_holders_count()
+        )?;
+        writeln!(
+            f,
+            "The actors are {}",
+            if self.is_paused() { "paused" } else { "running" }
         )
     }
 }
 
-impl<A> Deref for GlobalContext<A> where A: Actor {
+impl<A> Deref for GlobalContext<A> where A: UnsendActor {
     type Target = Inner<A>;
 
     #[inline]
@@ -204,7 +470,7 @@ impl<A> Deref for GlobalContext<A> where A: Actor {
     }
 }
 
-impl<A> Clone for GlobalContext<A> where A: Actor {
+impl<A> Clone for GlobalContext<A> where A: UnsendActor {
     fn clone(&self) -> Self {
         GlobalContext {
             inner: Arc::clone(&self.inner),
@@ -226,18 +492,51 @@ pub struct MessageRegister(
                 + Sync,
         >,
     >,
+    /// 已注册的消息类型名, 用于检查标识冲突
+    std::collections::HashMap<u64, &'static str>,
 );
 
 #[cfg(feature = "remote")]
 impl MessageRegister {
+    /// 注册消息`M`
+    ///
+    /// # Panics
+    /// 另一个类型已经以相同的标识注册时panic, 见[`MessageRegister::try_register`].
     pub fn register<M, A>(&mut self, addr: LocalAddress<A>)
     where
         M: ?Sized + RemoteType + crate::message::Message + 'static,
         A: crate::MessageHandler<M>,
         A::Output: RemoteType,
     {
+        if let Err(err) = self.try_register::<M, A>(addr) {
+            panic!("{}", err)
+        }
+    }
+
+    /// 注册消息`M`, 另一个类型已经以相同的标识注册时返回错误
+    ///
+    /// 同一个类型重复注册时替换之前的注册.
+    pub fn try_register<M, A>(&mut self, addr: LocalAddress<A>) -> Result<(), IdentityCollision>
+    where
+        M: RemoteType + crate::message::Message + 'static,
+        A: crate::MessageHandler<M>,
+        A::Output: RemoteType,
+    {
+        let id = M::identity_id();
+        let type_name = std::any::type_name::<M>();
+        match self.1.get(&id) {
+            Some(&existing) if existing != type_name => {
+                return Err(IdentityCollision {
+                    identity_id: id,
+                    existing,
+                    new: type_name,
+                })
+            }
+            _ => {}
+        }
+        self.1.insert(id, type_name);
         self.0.insert(
-            M::identity_id(),
+            id,
             Box::new(move |bytes| {
                 let addr = addr.clone();
                 let msg = deserialize::<M>(&bytes);
@@ -255,5 +554,36 @@ impl MessageRegister {
                 .boxed()
             }),
         );
+        Ok(())
     }
 }
+
+/// 两个消息类型的[`RemoteType::identity`]相同
+#[cfg(feature = "remote")]
+pub struct IdentityCollision {
+    pub identity_id: u64,
+    /// 已经注册的类型
+    pub existing: &'static str,
+    pub new: &'static str,
+}
+
+#[cfg(feature = "remote")]
+impl Debug for IdentityCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` cannot be registered because `{}` has the same identity ({:#x}).",
+            self.new, self.existing, self.identity_id
+        )
+    }
+}
+
+#[cfg(feature = "remote")]
+impl std::fmt::Display for IdentityCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "remote")]
+impl std::error::Error for IdentityCollision {}
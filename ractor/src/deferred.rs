@@ -0,0 +1,266 @@
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::{Future, FutureExt, StreamExt};
+use tokio::sync::oneshot;
+
+use crate::actor::panic_message;
+use crate::actor::UnsendActor;
+use crate::envelope::{Envelope, RespTx};
+use crate::meta::EnvelopeMeta;
+use crate::rt::Spawner;
+use crate::{Actor, Context};
+
+/// actor持有的, 在信箱循环之外执行的异步任务
+///
+/// `fut`在单独的任务中执行, actor处理消息期间也会继续推进.
+/// 完成之后产生一个继续处理的[`Envelope`], 由actor优先于信箱中的消息处理.
+/// 丢弃时(重置或停止)尚未完成的`fut`也会被取消.
+/// `fut`panic时没有后续的[`Envelope`], 响应被丢弃, actor继续运行.
+pub(crate) struct Deferred<A: ?Sized>
+where
+    A: UnsendActor,
+{
+    futures: FuturesUnordered<BoxFuture<'static, (Option<Envelope<A>>, bool)>>,
+    /// 正在执行的有序任务数量
+    ordered: usize,
+    /// 本次处理中最后一个任务接收响应通道的一端, 类型为`oneshot::Sender<RespTx<T>>`
+    reply: Option<Box<dyn Any + Send>>,
+}
+
+impl<A> Deferred<A>
+where
+    A: UnsendActor,
+{
+    #[inline]
+    pub fn new() -> Self {
+        Deferred {
+            futures: FuturesUnordered::new(),
+            ordered: 0,
+            reply: None,
+        }
+    }
+
+    /// 执行`fut`, 完成之后由actor执行`then`
+    ///
+    /// `then`的返回值发送给[`Deferred::adopt`]接管的响应通道.
+    pub fn push<F, C, T>(
+        &mut self,
+        spawner: &Spawner,
+        fut: F,
+        then: C,
+        meta: Option<Box<EnvelopeMeta>>,
+        ordered: bool,
+    ) where
+        A: Actor,
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+        C: for<'a> FnOnce(&'a mut A, F::Output, &'a mut Context<A>) -> BoxFuture<'a, T>
+            + Send
+            + 'static,
+        T: Send + 'static,
+    {
+        let (reply_tx, mut reply_rx) = oneshot::channel::<RespTx<T>>();
+        self.reply = Some(Box::new(reply_tx));
+        // 丢弃`handle`时取消`remote`
+        let (remote, handle) = AssertUnwindSafe(fut).catch_unwind().remote_handle();
+        spawner.spawn(remote);
+        self.futures.push(
+            async move {
+                let output = match handle.await {
+                    Ok(output) => output,
+                    Err(err) => {
+                        log::warn!(
+                            "the deferred future of `{}` panicked: {}",
+                            std::any::type_name::<A>(),
+                            panic_message(&*err).unwrap_or_default()
+                        );
+                        return (None, ordered);
+                    }
+                };
+                let envelope = Envelope::new::<F::Output, _>(
+                    move |actor: &mut A, ctx: &mut Context<A>| {
+                        Box::pin(async move {
+                            let resp = then(actor, output, ctx).await;
+                            // `then`中再次调用`defer`时, 响应继续交给新的任务
+                            let tx = reply_rx.try_recv().ok().and_then(|tx| ctx.deferred.adopt(tx));
+                            if let Some(tx) = tx {
+                                tx.send(resp)
+                                    .map_err(|_| (/* Response is discarded */))
+                                    .ok();
+                            }
+                        })
+                    },
+                )
+                .with_meta(meta);
+                (Some(envelope), ordered)
+            }
+            .boxed(),
+        );
+        if ordered {
+            self.ordered += 1;
+        }
+    }
+
+    /// 本次处理中调用了`defer`时, 最后一个任务接管`tx`, 否则原样返回`tx`
+    ///
+    /// 任务的返回值类型和`O`不同时不接管.
+    pub fn adopt<O>(&mut self, tx: RespTx<O>) -> Option<RespTx<O>>
+    where
+        O: Send + 'static,
+    {
+        let reply = match self.reply.take() {
+            Some(reply) => reply,
+            None => return Some(tx),
+        };
+        match reply.downcast::<oneshot::Sender<RespTx<O>>>() {
+            Ok(reply) => {
+                // 任务已经被取消时丢弃响应
+                reply.send(tx).ok();
+                None
+            }
+            Err(_) => {
+                log::warn!(
+                    "the deferred output of `{}` is not `{}`, the response is not taken over.",
+                    std::any::type_name::<A>(),
+                    std::any::type_name::<O>()
+                );
+                Some(tx)
+            }
+        }
+    }
+
+    /// 丢弃没有被接管的响应通道, 在处理每条消息之前调用
+    #[inline]
+    pub fn discard_reply(&mut self) {
+        self.reply = None;
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.futures.is_empty()
+    }
+
+    /// 有序任务完成之前不从信箱中取出消息
+    #[inline]
+    pub fn is_blocking(&self) -> bool {
+        self.ordered > 0
+    }
+
+    /// 等待下一个完成的任务
+    ///
+    /// 没有任务, 或者有序任务panic之后不再阻塞信箱时返回`None`.
+    pub async fn next(&mut self) -> Option<Envelope<A>> {
+        while let Some((envelope, ordered)) = self.futures.next().await {
+            if ordered {
+                self.ordered -= 1;
+            }
+            match envelope {
+                Some(envelope) => return Some(envelope),
+                None if ordered && !self.is_blocking() => return None,
+                None => {}
+            }
+        }
+        None
+    }
+
+    pub fn clear(&mut self) {
+        self.futures.clear();
+        self.ordered = 0;
+        self.reply = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use crate::{Actor, Broker, Context, MessageHandler};
+
+    #[derive(Default)]
+    struct Counter {
+        total: u32,
+    }
+
+    /// 为`true`时延后的任务panic
+    struct Fetch(bool);
+    /// 有序的任务panic
+    struct FetchOrdered;
+    struct Get;
+
+    impl Actor for Counter {
+        const MAIL_BOX_SIZE: u32 = 8;
+        type Args = ();
+
+        async fn create(_ctx: &mut Context<Self>) -> Self {
+            Counter::default()
+        }
+    }
+
+    impl MessageHandler<Fetch> for Counter {
+        type Output = u32;
+
+        async fn handle(&mut self, Fetch(panic): Fetch, ctx: &mut Context<Self>) -> u32 {
+            ctx.defer(
+                async move {
+                    assert!(!panic, "expected");
+                    5
+                },
+                |actor, n, _ctx| {
+                    async move {
+                        actor.total += n;
+                        actor.total
+                    }
+                    .boxed()
+                },
+            );
+            0
+        }
+    }
+
+    impl MessageHandler<FetchOrdered> for Counter {
+        type Output = u32;
+
+        async fn handle(&mut self, _: FetchOrdered, ctx: &mut Context<Self>) -> u32 {
+            ctx.defer_ordered(
+                async { panic!("expected") },
+                |actor, (), _ctx| async move { actor.total }.boxed(),
+            );
+            0
+        }
+    }
+
+    impl MessageHandler<Get> for Counter {
+        type Output = u32;
+
+        async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> u32 {
+            self.total
+        }
+    }
+
+    #[tokio::test]
+    async fn then_replies_to_caller() {
+        let broker = Broker::<Counter>::spawn_one().await;
+        assert_eq!(broker.call(Fetch(false)).await.unwrap(), 5);
+        assert_eq!(broker.call(Fetch(false)).await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn panic_drops_response_only() {
+        let broker = Broker::<Counter>::spawn_one().await;
+        broker.call(Fetch(false)).await.unwrap();
+        assert!(broker.call(Fetch(true)).await.is_err());
+        assert_eq!(broker.call(Get).await.unwrap(), 5);
+        let exits = broker.wait_for_actors().await;
+        assert_eq!(exits[0].restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn ordered_panic_unblocks_mailbox() {
+        let broker = Broker::<Counter>::spawn_one().await;
+        assert!(broker.call(FetchOrdered).await.is_err());
+        assert_eq!(broker.call(Get).await.unwrap(), 0);
+    }
+}
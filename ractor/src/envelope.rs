@@ -1,32 +1,620 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::pin::Pin;
+use std::task::Poll;
+
 use crossfire::mpmc::{RxFuture, SharedFutureBoth, TxFuture};
 use futures::future::BoxFuture;
-use tokio::sync::oneshot;
 
-use crate::message::{Message, MessageHandler};
-use crate::{Actor, Context};
+use crate::behavior::Unhandled;
+use crate::actor::UnsendActor;
+use crate::message::{Message, MessageHandler, UnsendHandler};
+use crate::meta::EnvelopeMeta;
+pub(crate) use crate::response::{RespRx, RespTx};
+use crate::Context;
+
+/// 内联保存的消息的大小, 更大或者对齐要求更高的消息会被装箱
+const INLINE_WORDS: usize = 4;
+
+type Inline = MaybeUninit<[usize; INLINE_WORDS]>;
+
+/// 打包好的消息
+///
+/// 消息和响应通道保存在信封内部, 由静态的[`VTable`]处理, 小于[`INLINE_WORDS`]个字的消息不分配内存.
+pub struct Envelope<A: ?Sized>
+where
+    A: UnsendActor,
+{
+    vtable: &'static VTable<A>,
+    meta: Option<Box<EnvelopeMeta>>,
+    payload: Inline,
+    /// 消息只要求`Send`
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+/// 某种[`Payload`]的操作
+struct VTable<A: ?Sized>
+where
+    A: UnsendActor,
+{
+    message_type: fn() -> &'static str,
+    /// 处理时产生的future不是`Send`的, 见[`FutureSlot::local`]
+    local: bool,
+    handle: for<'a, 'b> unsafe fn(
+        &'b mut Inline,
+        &'a mut A,
+        &'a mut Context<A>,
+        &'a mut FutureSlot,
+    ) -> Placed<'a>,
+    drop: unsafe fn(&mut Inline),
+}
+
+/// 信封中保存的内容
+trait Payload<A>: Send + Sized + 'static
+where
+    A: UnsendActor,
+{
+    /// 处理时产生的future不是`Send`的
+    const LOCAL: bool = false;
+
+    fn message_type() -> &'static str;
+
+    fn handle<'a>(
+        self,
+        actor: &'a mut A,
+        ctx: &'a mut Context<A>,
+        slot: &'a mut FutureSlot,
+    ) -> Placed<'a>;
+}
+
+struct VTableOf<A, P>(PhantomData<fn() -> (A, P)>);
+
+impl<A, P> VTableOf<A, P>
+where
+    A: UnsendActor,
+    P: Payload<A>,
+{
+    const VTABLE: VTable<A> = VTable {
+        message_type: P::message_type,
+        local: P::LOCAL,
+        handle: handle_payload::<A, P>,
+        drop: drop_payload::<P>,
+    };
+}
+
+#[inline]
+const fn is_inline<P>() -> bool {
+    size_of::<P>() <= size_of::<Inline>() && align_of::<P>() <= align_of::<Inline>()
+}
+
+#[inline]
+fn store<P>(payload: P) -> Inline {
+    let mut buf = Inline::uninit();
+    unsafe {
+        if is_inline::<P>() {
+            buf.as_mut_ptr().cast::<P>().write(payload);
+        } else {
+            buf.as_mut_ptr().cast::<Box<P>>().write(Box::new(payload));
+        }
+    }
+    buf
+}
+
+/// 取出`buf`中的`P`, 之后`buf`不能再被使用
+#[inline]
+unsafe fn take<P>(buf: &mut Inline) -> P {
+    if is_inline::<P>() {
+        buf.as_ptr().cast::<P>().read()
+    } else {
+        *buf.as_ptr().cast::<Box<P>>().read()
+    }
+}
+
+unsafe fn handle_payload<'a, A, P>(
+    buf: &mut Inline,
+    actor: &'a mut A,
+    ctx: &'a mut Context<A>,
+    slot: &'a mut FutureSlot,
+) -> Placed<'a>
+where
+    A: UnsendActor,
+    P: Payload<A>,
+{
+    take::<P>(buf).handle(actor, ctx, slot)
+}
+
+unsafe fn drop_payload<P>(buf: &mut Inline) {
+    drop(take::<P>(buf))
+}
+
+impl<A> Envelope<A>
+where
+    A: UnsendActor,
+{
+    #[inline]
+    fn from_payload<P>(payload: P) -> Self
+    where
+        P: Payload<A>,
+    {
+        Envelope {
+            vtable: &VTableOf::<A, P>::VTABLE,
+            meta: None,
+            payload: store(payload),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// 由闭包处理的消息, 类型名为`N`
+    #[inline]
+    pub(crate) fn new<N, F>(handle: F) -> Self
+    where
+        N: 'static,
+        F: for<'a> FnOnce(&'a mut A, &'a mut Context<A>) -> BoxFuture<'a, ()> + Send + 'static,
+    {
+        Envelope::from_payload(Closure::<N, F>(handle, PhantomData))
+    }
+
+    #[inline]
+    pub(crate) fn with_meta(mut self, meta: Option<Box<EnvelopeMeta>>) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn take_meta(&mut self) -> Option<Box<EnvelopeMeta>> {
+        self.meta.take()
+    }
+
+    /// 开始处理消息, 返回的future保存在`slot`中
+    #[inline]
+    pub(crate) fn handle<'a>(
+        self,
+        actor: &'a mut A,
+        ctx: &'a mut Context<A>,
+        slot: &'a mut FutureSlot,
+    ) -> Placed<'a> {
+        if self.vtable.local && !slot.local {
+            log::error!(
+                "`{}` can only be handled by `{}` spawned by `UnsendBroker`.",
+                self.message_type(),
+                std::any::type_name::<A>()
+            );
+            return slot.place(std::future::ready(()));
+        }
+        let mut this = ManuallyDrop::new(self);
+        drop(this.meta.take());
+        // `handle`取出了消息, 不会再调用`drop`
+        unsafe { (this.vtable.handle)(&mut this.payload, actor, ctx, slot) }
+    }
+}
 
-pub type Envelope<A> =
-    Box<dyn for<'a> FnOnce(&'a mut A, &'a mut Context<A>) -> BoxFuture<'a, ()> + Send>;
+impl<A: ?Sized> Drop for Envelope<A>
+where
+    A: UnsendActor,
+{
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { (self.vtable.drop)(&mut self.payload) }
+    }
+}
+
+impl<A: ?Sized> Envelope<A>
+where
+    A: UnsendActor,
+{
+    /// 消息的类型名, 见[`std::any::type_name`]
+    #[inline]
+    pub fn message_type(&self) -> &'static str {
+        (self.vtable.message_type)()
+    }
+
+    /// 发送时附带的元数据
+    #[inline]
+    pub fn meta(&self) -> Option<&EnvelopeMeta> {
+        self.meta.as_deref()
+    }
+}
 
 pub(crate) fn pack<A, M>(msg: M) -> (Envelope<A>, RespRx<<A as MessageHandler<M>>::Output>)
 where
     M: Message + 'static,
-    A: Actor + MessageHandler<M>,
+    A: MessageHandler<M>,
+{
+    let (tx, rx) = crate::response::channel();
+    (pack_with(msg, Some(tx)), rx)
+}
+
+/// 打包不需要响应的消息
+///
+/// 和[`pack`]相比省去了响应通道.
+#[inline]
+pub(crate) fn pack_detached<A, M>(msg: M) -> Envelope<A>
+where
+    M: Message + 'static,
+    A: MessageHandler<M>,
+{
+    pack_with(msg, None)
+}
+
+/// 使用已有的响应通道打包消息
+///
+/// `tx`为`None`时响应会被丢弃
+#[inline]
+pub(crate) fn pack_with<A, M>(
+    msg: M,
+    tx: Option<RespTx<<A as MessageHandler<M>>::Output>>,
+) -> Envelope<A>
+where
+    M: Message + 'static,
+    A: MessageHandler<M>,
+{
+    Envelope::from_payload(Packed::<A, M, false> { msg, tx })
+}
+
+/// 打包由[`UnsendHandler`]处理的消息, 只能由[`FutureSlot::local`]处理
+#[inline]
+pub(crate) fn pack_local<A, M>(
+    msg: M,
+    tx: Option<RespTx<<A as UnsendHandler<M>>::Output>>,
+) -> Envelope<A>
+where
+    M: Message + 'static,
+    A: UnsendHandler<M>,
+{
+    Envelope::from_payload(Packed::<A, M, true> { msg, tx })
+}
+
+/// 消息和它的响应通道
+///
+/// `LOCAL`为`false`时只由[`pack_with`]创建, `A`实现了[`MessageHandler`], 处理的future是`Send`的.
+struct Packed<A, M, const LOCAL: bool>
+where
+    M: Message + 'static,
+    A: UnsendHandler<M>,
+{
+    msg: M,
+    tx: Option<RespTx<<A as UnsendHandler<M>>::Output>>,
+}
+
+impl<A, M, const LOCAL: bool> Payload<A> for Packed<A, M, LOCAL>
+where
+    M: Message + 'static,
+    A: UnsendHandler<M>,
 {
-    let (tx, rx) = oneshot::channel();
-    (
-        Box::new(move |actor: &mut A, ctx: &mut Context<A>| {
-            Box::pin(async move {
-                let resp = <A as MessageHandler<M>>::handle(actor, msg, ctx).await;
+    const LOCAL: bool = LOCAL;
+
+    #[inline]
+    fn message_type() -> &'static str {
+        std::any::type_name::<M>()
+    }
+
+    #[inline]
+    fn handle<'a>(
+        self,
+        actor: &'a mut A,
+        ctx: &'a mut Context<A>,
+        slot: &'a mut FutureSlot,
+    ) -> Placed<'a> {
+        dispatch::<A, M, LOCAL>(self.msg, self.tx, actor, ctx, slot)
+    }
+}
+
+/// 由闭包处理的消息, 例如[`Context::defer`]的`then`
+struct Closure<N, F>(F, PhantomData<fn() -> N>);
+
+impl<A, N, F> Payload<A> for Closure<N, F>
+where
+    A: UnsendActor,
+    N: 'static,
+    F: for<'a> FnOnce(&'a mut A, &'a mut Context<A>) -> BoxFuture<'a, ()> + Send + 'static,
+{
+    #[inline]
+    fn message_type() -> &'static str {
+        std::any::type_name::<N>()
+    }
+
+    #[inline]
+    fn handle<'a>(
+        self,
+        actor: &'a mut A,
+        ctx: &'a mut Context<A>,
+        slot: &'a mut FutureSlot,
+    ) -> Placed<'a> {
+        slot.place((self.0)(actor, ctx))
+    }
+}
+
+/// 处理消息并发送响应
+///
+/// 被取消或者不处理的消息在这里同步完成.
+fn dispatch<'a, A, M, const LOCAL: bool>(
+    msg: M,
+    tx: Option<RespTx<<A as UnsendHandler<M>>::Output>>,
+    actor: &'a mut A,
+    ctx: &'a mut Context<A>,
+    slot: &'a mut FutureSlot,
+) -> Placed<'a>
+where
+    M: Message + 'static,
+    A: UnsendHandler<M>,
+{
+    let cancellable = <A as UnsendHandler<M>>::CANCELLABLE;
+    if cancellable && tx.as_ref().is_some_and(RespTx::is_closed) {
+        log::debug!(
+            "`{}` is cancelled before being handled by `{}`.",
+            std::any::type_name::<M>(),
+            std::any::type_name::<A>()
+        );
+        return slot.place(std::future::ready(()));
+    }
+    if let Some(unhandled) = <A as UnsendHandler<M>>::unhandled(actor, ctx) {
+        fallback::<A, M, LOCAL>(msg, tx, unhandled, ctx);
+        return slot.place(std::future::ready(()));
+    }
+    slot.place(async move {
+        // 处理期间上下文可以检查是否被取消
+        if cancellable {
+            ctx.cancellation = tx.as_ref().map(RespTx::cancellation);
+        }
+        let resp = <A as UnsendHandler<M>>::handle(actor, msg, ctx).await;
+        ctx.cancellation = None;
+        // 消息在处理时被暂存的话, 响应交由暂存的消息发送
+        let tx = match tx {
+            Some(tx) if ctx.stash.has_pending() => {
+                ctx.stash.adopt(tx, repack_current::<A, M, LOCAL>)
+            }
+            tx => tx,
+        };
+        // 处理时调用了`Context::defer`的话, 响应交由最后一个任务发送
+        let tx = tx.and_then(|tx| ctx.deferred.adopt(tx));
+        if let Some(tx) = tx {
+            tx.send(resp)
+                .map_err(|_| (/* Response is discarded */))
+                .ok();
+        }
+    })
+}
+
+/// 重新打包被[`Context::stash_current`]暂存的消息, 保留原来的`LOCAL`
+fn repack_current<A, M, const LOCAL: bool>(
+    msg: M,
+    tx: RespTx<<A as UnsendHandler<M>>::Output>,
+) -> Envelope<A>
+where
+    M: Message + 'static,
+    A: UnsendHandler<M>,
+{
+    Envelope::from_payload(Packed::<A, M, LOCAL> { msg, tx: Some(tx) })
+}
+
+/// 当前行为不处理消息时
+fn fallback<A, M, const LOCAL: bool>(
+    msg: M,
+    tx: Option<RespTx<<A as UnsendHandler<M>>::Output>>,
+    unhandled: Unhandled<<A as UnsendHandler<M>>::Output>,
+    ctx: &mut Context<A>,
+) where
+    M: Message + 'static,
+    A: UnsendHandler<M>,
+{
+    match unhandled {
+        Unhandled::Stash if !ctx.stash.is_full() => {
+            let meta = ctx.meta.take();
+            let envelope = Envelope::from_payload(Packed::<A, M, LOCAL> { msg, tx });
+            ctx.stash.push_envelope(envelope.with_meta(meta));
+        }
+        Unhandled::Reply(resp) => {
+            if let Some(tx) = tx {
                 tx.send(resp)
                     .map_err(|_| (/* Response is discarded */))
                     .ok();
-            })
-        }),
-        rx,
-    )
+            }
+        }
+        Unhandled::Stash | Unhandled::DeadLetter => {
+            log::warn!(
+                "dead letter: `{}` is not handled in the current behavior of `{}`.",
+                std::any::type_name::<M>(),
+                std::any::type_name::<A>()
+            );
+        }
+    }
+}
+
+/// 复用的future存储
+///
+/// 每个actor一个, 只在遇到更大的future时重新分配, 所以稳定之后处理消息不再分配内存.
+pub(crate) struct FutureSlot {
+    buf: Vec<MaybeUninit<SlotUnit>>,
+    /// 有[`Placed`]正在使用
+    occupied: bool,
+    /// 可以放入不是`Send`的future
+    local: bool,
+}
+
+/// 对齐要求不超过它的future可以放入[`FutureSlot`]
+#[repr(align(16))]
+struct SlotUnit {
+    _bytes: [u8; 16],
+}
+
+impl FutureSlot {
+    #[inline]
+    pub fn new() -> Self {
+        FutureSlot {
+            buf: Vec::new(),
+            occupied: false,
+            local: false,
+        }
+    }
+
+    /// 同时处理[`UnsendHandler`]的消息
+    ///
+    /// 这样的actor只能通过`spawn_local`运行, 不会在线程之间移动.
+    #[inline]
+    pub fn local() -> Self {
+        FutureSlot {
+            local: true,
+            ..FutureSlot::new()
+        }
+    }
+
+    /// 放入`fut`, 对齐要求过高时装箱
+    ///
+    /// 不是[`FutureSlot::local`]时, 只能放入`Send`的future.
+    fn place<'a, F>(&'a mut self, fut: F) -> Placed<'a>
+    where
+        F: Future<Output = ()> + 'a,
+    {
+        if align_of::<F>() > align_of::<SlotUnit>() {
+            let fut: Pin<Box<dyn Future<Output = ()> + 'a>> = Box::pin(fut);
+            return self.place(fut);
+        }
+        let units = size_of::<F>().div_ceil(size_of::<SlotUnit>());
+        // `Placed`被忘记时, 旧的future没有被丢弃, 不能覆盖它
+        if self.occupied {
+            std::mem::forget(std::mem::take(&mut self.buf));
+        }
+        if self.buf.len() < units {
+            self.buf = Vec::with_capacity(units);
+            self.buf.resize_with(units, MaybeUninit::uninit);
+        }
+        let ptr = self.buf.as_mut_ptr().cast::<F>();
+        unsafe { ptr.write(fut) };
+        self.occupied = true;
+        Placed {
+            fut: ptr as *mut (dyn Future<Output = ()> + 'a),
+            occupied: &mut self.occupied,
+        }
+    }
+}
+
+/// 保存在[`FutureSlot`]中的future, 完成或丢弃时在原地析构
+pub(crate) struct Placed<'a> {
+    fut: *mut (dyn Future<Output = ()> + 'a),
+    occupied: &'a mut bool,
+}
+
+// 不是`Send`的future只能放入`FutureSlot::local`, 它所在的actor不会在线程之间移动
+unsafe impl Send for Placed<'_> {}
+
+impl Future for Placed<'_> {
+    type Output = ();
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        let fut = self.fut;
+        // `FutureSlot`在`Placed`丢弃之前不会移动或覆盖这块内存
+        unsafe { Pin::new_unchecked(&mut *fut) }.poll(cx)
+    }
+}
+
+impl Drop for Placed<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { std::ptr::drop_in_place(self.fut) };
+        *self.occupied = false;
+    }
 }
 
 pub type MailBoxTx<A> = TxFuture<Envelope<A>, SharedFutureBoth>;
 pub type MailBoxRx<A> = RxFuture<Envelope<A>, SharedFutureBoth>;
-pub type RespRx<O> = oneshot::Receiver<O>;
+
+#[cfg(all(test, feature = "rt-tokio"))]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::future::Future;
+
+    use crate::{Actor, Broker, Context, LocalAddress, MessageHandler};
+
+    /// 统计当前线程在计数期间的分配次数
+    struct Counting;
+
+    thread_local! {
+        static COUNTING: Cell<bool> = const { Cell::new(false) };
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if COUNTING.try_with(Cell::get).unwrap_or(false) {
+                ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            }
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+
+    async fn count_allocations(fut: impl Future<Output = ()>) -> usize {
+        ALLOCATIONS.with(|count| count.set(0));
+        COUNTING.with(|counting| counting.set(true));
+        fut.await;
+        COUNTING.with(|counting| counting.set(false));
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    const BATCH: u64 = 32;
+
+    struct Echo;
+
+    struct Ping(u64);
+
+    impl Actor for Echo {
+        const MAIL_BOX_SIZE: u32 = BATCH as u32;
+        type Args = ();
+
+        async fn create(_ctx: &mut Context<Self>) -> Self {
+            Echo
+        }
+    }
+
+    impl MessageHandler<Ping> for Echo {
+        type Output = u64;
+
+        async fn handle(&mut self, Ping(n): Ping, _ctx: &mut Context<Self>) -> u64 {
+            n
+        }
+    }
+
+    async fn round(addr: &LocalAddress<Echo>, handles: &mut Vec<crate::ResponseHandle<u64>>) {
+        for n in 0..BATCH {
+            handles.push(addr.send(Ping(n)).await.unwrap());
+        }
+        for (n, handle) in (0..).zip(handles.drain(..)) {
+            assert_eq!(handle.recv().await.unwrap(), n);
+        }
+        for n in 0..BATCH {
+            addr.do_send(Ping(n)).await.unwrap();
+        }
+        assert_eq!(addr.call(Ping(BATCH)).await.unwrap(), BATCH);
+    }
+
+    /// 单线程运行时中actor和调用者在同一个线程, 全部的分配都会被统计
+    #[tokio::test]
+    async fn dispatch_does_not_allocate() {
+        let broker = Broker::<Echo>::spawn(1, false).await;
+        let addr = broker.addr().clone();
+        let mut handles = Vec::with_capacity(BATCH as usize);
+        // 填满响应池和future的空间
+        for _ in 0..4 {
+            round(&addr, &mut handles).await;
+        }
+
+        let allocations = count_allocations(round(&addr, &mut handles)).await;
+        // 只剩crossfire在空信箱上等待时分配的waker, 每轮只有几次, 和消息数量无关
+        assert!(
+            allocations <= 8,
+            "{} allocations for {} messages",
+            allocations,
+            2 * BATCH + 1
+        );
+    }
+}
@@ -5,20 +5,30 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum Error {}
 
-pub struct ChannelSendError<T>(T);
+pub enum ChannelSendError<T> {
+    Disconnected(T),
+    /// 超出了[`LimitMode::Reject`](crate::limiter::LimitMode::Reject)的限速, 消息没有进入信箱
+    RateLimited(T),
+}
 
 impl<T> From<crossfire::mpmc::SendError<T>> for ChannelSendError<T> {
     fn from(err: crossfire::mpmc::SendError<T>) -> Self {
-        ChannelSendError(err.0)
+        ChannelSendError::Disconnected(err.0)
     }
 }
 
 impl<T> Debug for ChannelSendError<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "The message could not be sent because the channel is disconnected."
-        )
+        match self {
+            ChannelSendError::Disconnected(_) => write!(
+                f,
+                "The message could not be sent because the channel is disconnected."
+            ),
+            ChannelSendError::RateLimited(_) => write!(
+                f,
+                "The message could not be sent because the rate limit has been exceeded."
+            ),
+        }
     }
 }
 
@@ -32,13 +42,18 @@ impl<A> std::error::Error for ChannelSendError<A> {}
 
 impl<T> ChannelSendError<T> {
     pub fn recover(self) -> T {
-        self.0
+        match self {
+            ChannelSendError::Disconnected(t) => t,
+            ChannelSendError::RateLimited(t) => t,
+        }
     }
 }
 
 pub enum ChannelTrySendError<T> {
     Full(T),
     Disconnected(T),
+    /// 超出了[`LimitMode::Reject`](crate::limiter::LimitMode::Reject)的限速, 消息没有进入信箱
+    RateLimited(T),
 }
 
 impl<T> From<crossfire::mpmc::TrySendError<T>> for ChannelTrySendError<T> {
@@ -52,23 +67,74 @@ impl<T> From<crossfire::mpmc::TrySendError<T>> for ChannelTrySendError<T> {
 
 impl<T> Debug for ChannelTrySendError<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "The message could not be sent because the channel is {}.",
-            if matches!(self, ChannelTrySendError::Full(_)) {
-                "full"
-            } else {
-                "disconnected"
-            }
-        )
+        match self {
+            ChannelTrySendError::Full(_) => write!(
+                f,
+                "The message could not be sent because the channel is full."
+            ),
+            ChannelTrySendError::Disconnected(_) => write!(
+                f,
+                "The message could not be sent because the channel is disconnected."
+            ),
+            ChannelTrySendError::RateLimited(_) => write!(
+                f,
+                "The message could not be sent because the rate limit has been exceeded."
+            ),
+        }
     }
 }
 
+impl<T> Display for ChannelTrySendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<T> std::error::Error for ChannelTrySendError<T> {}
+
 impl<T> ChannelTrySendError<T> {
     pub fn recover(self) -> T {
         match self {
             ChannelTrySendError::Full(t) => t,
             ChannelTrySendError::Disconnected(t) => t,
+            ChannelTrySendError::RateLimited(t) => t,
+        }
+    }
+
+    pub(crate) fn map<U, F>(self, f: F) -> ChannelTrySendError<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            ChannelTrySendError::Full(t) => ChannelTrySendError::Full(f(t)),
+            ChannelTrySendError::Disconnected(t) => ChannelTrySendError::Disconnected(f(t)),
+            ChannelTrySendError::RateLimited(t) => ChannelTrySendError::RateLimited(f(t)),
         }
     }
 }
+
+/// 暂存区已满
+pub struct StashFull<T>(pub(crate) T);
+
+impl<T> Debug for StashFull<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The message could not be stashed because the stash is full."
+        )
+    }
+}
+
+impl<T> Display for StashFull<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<T> std::error::Error for StashFull<T> {}
+
+impl<T> StashFull<T> {
+    pub fn recover(self) -> T {
+        self.0
+    }
+}
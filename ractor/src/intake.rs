@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::Waker;
+
+use crate::interceptor::{Interceptor, Interceptors};
+use crate::limiter::{LimitMode, RateLimiter};
+use crate::pause::PauseGate;
+
+/// 信箱的入口, 控制同一个地址的全部actor从信箱中取出消息
+#[derive(Default)]
+pub(crate) struct Intake {
+    gate: PauseGate,
+    limiter: RwLock<Option<Arc<RateLimiter>>>,
+    interceptors: RwLock<Option<Interceptors>>,
+    /// 等待信箱有空位的任务
+    space_wakers: Mutex<Vec<Waker>>,
+    space_waiting: AtomicBool,
+}
+
+impl Intake {
+    #[inline]
+    pub fn pause(&self) {
+        self.gate.pause()
+    }
+
+    #[inline]
+    pub fn resume(&self) {
+        self.gate.resume()
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.gate.is_paused()
+    }
+
+    /// 等待直到没有暂停
+    #[inline]
+    pub async fn wait(&self) {
+        self.gate.wait().await
+    }
+
+    #[inline]
+    pub fn set_rate_limiter(&self, limiter: Option<RateLimiter>) {
+        *self.limiter.write().unwrap() = limiter.map(Arc::new);
+    }
+
+    #[inline]
+    pub fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        self.limiter.read().unwrap().clone()
+    }
+
+    /// 发送消息之前调用
+    ///
+    /// 返回`false`表示超出了[`LimitMode::Reject`]的限制, 消息不应该进入信箱.
+    pub fn try_enter(&self) -> bool {
+        match &*self.limiter.read().unwrap() {
+            Some(limiter) if limiter.mode() == LimitMode::Reject => limiter.try_acquire(),
+            _ => true,
+        }
+    }
+
+    /// 等待取出的消息可以被处理
+    ///
+    /// [`LimitMode::Reject`]已经在[`Intake::try_enter`]中检查过, 这里不再限制.
+    pub async fn admit(&self) {
+        self.wait().await;
+        if let Some(limiter) = self.rate_limiter() {
+            if limiter.mode() != LimitMode::Reject {
+                limiter.acquire().await.ok();
+            }
+        }
+    }
+
+    pub fn add_interceptor(&self, interceptor: Arc<dyn Interceptor>) {
+        let mut interceptors = self.interceptors.write().unwrap();
+        let mut list = interceptors
+            .as_deref()
+            .map(<[_]>::to_vec)
+            .unwrap_or_default();
+        list.push(interceptor);
+        *interceptors = Some(list.into());
+    }
+
+    #[inline]
+    pub fn clear_interceptors(&self) {
+        *self.interceptors.write().unwrap() = None;
+    }
+
+    #[inline]
+    pub fn interceptors(&self) -> Option<Interceptors> {
+        self.interceptors.read().unwrap().clone()
+    }
+
+    /// 信箱有空位时唤醒`waker`
+    #[cfg(feature = "tower")]
+    pub fn register_space(&self, waker: &Waker) {
+        let mut wakers = self.space_wakers.lock().unwrap();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+        self.space_waiting.store(true, Ordering::Release);
+    }
+
+    /// 从信箱中取出消息之后调用
+    #[inline]
+    pub fn notify_space(&self) {
+        if self.space_waiting.swap(false, Ordering::AcqRel) {
+            let wakers = std::mem::take(&mut *self.space_wakers.lock().unwrap());
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
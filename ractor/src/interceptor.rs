@@ -0,0 +1,82 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use tokio::time::Instant;
+
+/// 在actor处理每条消息的前后执行, 用于鉴权, 审计, 计时等
+///
+/// 通过[`LocalAddress::intercept`](crate::LocalAddress::intercept)添加到地址上,
+/// 同一个地址的全部actor共享.
+/// `before`按照添加的顺序执行, `after`按照相反的顺序执行.
+///
+/// ```ignore
+/// struct Timing;
+///
+/// impl Interceptor for Timing {
+///     fn after(&self, info: &MessageInfo) {
+///         log::info!("{} took {:?}", info.message_type, info.received_at.elapsed());
+///     }
+/// }
+///
+/// broker.intercept(Timing);
+/// ```
+pub trait Interceptor: Send + Sync + 'static {
+    /// 处理消息之前
+    ///
+    /// 返回[`Rejection`]时消息会被丢弃, 之后的拦截器和`after`都不会执行,
+    /// 等待响应的一方会收到[`HandlerPanic`](crate::HandlerPanic).
+    #[inline]
+    fn before(&self, _info: &MessageInfo) -> Result<(), Rejection> {
+        Ok(())
+    }
+
+    /// 处理消息之后
+    #[inline]
+    fn after(&self, _info: &MessageInfo) {}
+}
+
+/// 正在处理的消息
+#[derive(Clone, Debug)]
+pub struct MessageInfo {
+    pub actor_type: &'static str,
+    /// 见[`std::any::type_name`]
+    pub message_type: &'static str,
+    /// 开始处理的时间
+    pub received_at: Instant,
+}
+
+/// 拒绝处理消息
+#[derive(Clone)]
+pub struct Rejection {
+    reason: String,
+}
+
+impl Rejection {
+    #[inline]
+    pub fn new(reason: impl Into<String>) -> Self {
+        Rejection {
+            reason: reason.into(),
+        }
+    }
+
+    #[inline]
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl Debug for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The message is rejected: {}", self.reason)
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+pub(crate) type Interceptors = Arc<[Arc<dyn Interceptor>]>;
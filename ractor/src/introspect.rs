@@ -0,0 +1,423 @@
+//! 查看运行中的actor
+//!
+//! 需要`introspect` feature. 未启用时只记录重启次数, 处理消息时没有额外的开销.
+//!
+//! [`snapshot`]列出所有存活的broker, 包括信箱的积压情况, 每个actor所处的生命周期阶段,
+//! [`State`], 重启次数和正在处理的消息. 快照可以序列化, 便于通过调试接口导出.
+//!
+//! broker还在时, 最近结束的[`KEEP_STOPPED`]个actor也会以[`Phase::Stopped`]出现在快照中,
+//! 更早结束的actor会被移除.
+//!
+//! ```ignore
+//! let snapshot = ractor::introspect::snapshot();
+//! println!("{}", serde_json::to_string_pretty(&snapshot)?);
+//! ```
+
+#[cfg(feature = "introspect")]
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "introspect")]
+use std::sync::atomic::AtomicU8;
+#[cfg(feature = "introspect")]
+use std::sync::{Mutex, Weak};
+
+#[cfg(feature = "introspect")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "introspect")]
+use crate::context::Inner;
+#[cfg(feature = "introspect")]
+use crate::intake::Intake;
+#[cfg(feature = "introspect")]
+use crate::actor::UnsendActor;
+use crate::State;
+
+#[cfg(feature = "introspect")]
+static REGISTRY: Mutex<Vec<Weak<dyn Inspect>>> = Mutex::new(Vec::new());
+
+/// 每个broker在快照中保留的已结束的actor数量
+#[cfg(feature = "introspect")]
+pub const KEEP_STOPPED: usize = 16;
+
+/// 所有存活的broker
+#[cfg(feature = "introspect")]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SystemSnapshot {
+    pub brokers: Vec<BrokerSnapshot>,
+}
+
+/// 共享同一个信箱的一组actor
+#[cfg(feature = "introspect")]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BrokerSnapshot {
+    pub actor_type: String,
+    /// 存活的actor数量
+    pub instances: usize,
+    /// 信箱中等待处理的消息数量, actor全部结束之后为0
+    pub mailbox_depth: usize,
+    pub mailbox_capacity: usize,
+    /// 是否被[`LocalAddress::pause`](crate::LocalAddress::pause)暂停
+    pub paused: bool,
+    pub actors: Vec<ActorSnapshot>,
+}
+
+#[cfg(feature = "introspect")]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActorSnapshot {
+    /// 在所属broker中的编号
+    pub id: usize,
+    pub phase: Phase,
+    pub state: StateKind,
+    /// 重启次数, 包括[`State::Reset`]
+    pub restarts: u32,
+    /// panic之后的重启次数, 计入[`Actor::MAX_RESTARTS`](crate::Actor::MAX_RESTARTS)
+    pub panic_restarts: u32,
+    /// 正在处理的消息类型
+    pub current_message: Option<String>,
+}
+
+/// 生命周期阶段
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "introspect", derive(Serialize, Deserialize))]
+pub enum Phase {
+    Creating,
+    Starting,
+    /// 等待消息
+    Idle,
+    Handling,
+    Stopping,
+    Stopped,
+}
+
+/// 不包含数据的[`State`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "introspect", derive(Serialize, Deserialize))]
+pub enum StateKind {
+    Continue,
+    Stop,
+    Pause,
+    Yield,
+    Reset,
+    Abort,
+    Sleep,
+}
+
+impl From<&State> for StateKind {
+    fn from(state: &State) -> Self {
+        match state {
+            State::Continue => StateKind::Continue,
+            State::Stop => StateKind::Stop,
+            State::Pause(_) => StateKind::Pause,
+            State::Yield => StateKind::Yield,
+            State::Reset => StateKind::Reset,
+            State::Abort => StateKind::Abort,
+            State::Sleep(_) => StateKind::Sleep,
+        }
+    }
+}
+
+/// 当前所有存活的broker的快照
+#[cfg(feature = "introspect")]
+pub fn snapshot() -> SystemSnapshot {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|inspect| inspect.strong_count() > 0);
+    SystemSnapshot {
+        brokers: registry
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|inspect| inspect.snapshot())
+            .collect(),
+    }
+}
+
+/// 登记到[`snapshot`]中
+///
+/// 返回的登记由broker和`Inner`持有, 两者都不存在之后自动移除.
+#[cfg(feature = "introspect")]
+pub(crate) fn register<A>(
+    inner: Weak<Inner<A>>,
+    monitor: Arc<Monitor>,
+    intake: Arc<Intake>,
+) -> Registration
+where
+    A: UnsendActor,
+{
+    let registration: Registration = Arc::new(Entry {
+        inner,
+        monitor,
+        intake,
+    });
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|inspect| inspect.strong_count() > 0);
+    registry.push(Arc::downgrade(&registration));
+    registration
+}
+
+#[cfg(feature = "introspect")]
+pub(crate) type Registration = Arc<dyn Inspect>;
+
+#[cfg(feature = "introspect")]
+pub(crate) trait Inspect: Send + Sync {
+    fn snapshot(&self) -> BrokerSnapshot;
+}
+
+/// 只持有`Inner`的弱引用, 不会让信箱保持打开
+#[cfg(feature = "introspect")]
+struct Entry<A>
+where
+    A: UnsendActor,
+{
+    inner: Weak<Inner<A>>,
+    monitor: Arc<Monitor>,
+    intake: Arc<Intake>,
+}
+
+#[cfg(feature = "introspect")]
+impl<A> Inspect for Entry<A>
+where
+    A: UnsendActor,
+{
+    fn snapshot(&self) -> BrokerSnapshot {
+        let actors: Vec<_> = self.monitor.actors().iter().map(|a| a.snapshot()).collect();
+        BrokerSnapshot {
+            actor_type: std::any::type_name::<A>().to_owned(),
+            instances: actors
+                .iter()
+                .filter(|actor| actor.phase != Phase::Stopped)
+                .count(),
+            mailbox_depth: self
+                .inner
+                .upgrade()
+                .map_or(0, |inner| inner.recipient.len()),
+            mailbox_capacity: A::MAIL_BOX_SIZE as usize,
+            paused: self.intake.is_paused(),
+            actors,
+        }
+    }
+}
+
+/// 同一个broker的全部actor的状态
+#[derive(Default)]
+pub(crate) struct Monitor {
+    next_id: AtomicUsize,
+    #[cfg(feature = "introspect")]
+    actors: Mutex<Actors>,
+}
+
+#[cfg(feature = "introspect")]
+#[derive(Default)]
+struct Actors {
+    live: Vec<Arc<ActorStatus>>,
+    /// 最近结束的actor, 最多[`KEEP_STOPPED`]个
+    stopped: VecDeque<Arc<ActorStatus>>,
+}
+
+impl Monitor {
+    pub fn add(&self) -> Arc<ActorStatus> {
+        let status = Arc::new(ActorStatus {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            restarts: AtomicU32::new(0),
+            panic_restarts: AtomicU32::new(0),
+            #[cfg(feature = "introspect")]
+            phase: AtomicU8::new(Phase::Creating as u8),
+            #[cfg(feature = "introspect")]
+            state: AtomicU8::new(StateKind::Continue as u8),
+            #[cfg(feature = "introspect")]
+            current_message: Mutex::new(None),
+        });
+        #[cfg(feature = "introspect")]
+        self.actors.lock().unwrap().live.push(Arc::clone(&status));
+        status
+    }
+
+    /// actor的上下文被丢弃, 包括正常结束和被停止的任务
+    pub fn remove(&self, status: &Arc<ActorStatus>) {
+        status.set_phase(Phase::Stopped);
+        #[cfg(feature = "introspect")]
+        {
+            let mut actors = self.actors.lock().unwrap();
+            if let Some(index) = actors.live.iter().position(|a| Arc::ptr_eq(a, status)) {
+                let status = actors.live.swap_remove(index);
+                if actors.stopped.len() == KEEP_STOPPED {
+                    actors.stopped.pop_front();
+                }
+                actors.stopped.push_back(status);
+            }
+        }
+    }
+
+    #[cfg(feature = "introspect")]
+    fn actors(&self) -> Vec<Arc<ActorStatus>> {
+        let actors = self.actors.lock().unwrap();
+        let mut list: Vec<_> = actors.live.iter().chain(&actors.stopped).cloned().collect();
+        list.sort_unstable_by_key(|status| status.id);
+        list
+    }
+}
+
+/// 单个actor的状态, 由[`ActorRunner`](crate::actor_runner::ActorRunner)更新
+///
+/// 没有`introspect` feature时只记录编号和重启次数.
+pub(crate) struct ActorStatus {
+    id: usize,
+    restarts: AtomicU32,
+    panic_restarts: AtomicU32,
+    #[cfg(feature = "introspect")]
+    phase: AtomicU8,
+    #[cfg(feature = "introspect")]
+    state: AtomicU8,
+    #[cfg(feature = "introspect")]
+    current_message: Mutex<Option<&'static str>>,
+}
+
+impl ActorStatus {
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    #[inline]
+    pub fn restarts(&self) -> u32 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn panic_restarts(&self) -> u32 {
+        self.panic_restarts.load(Ordering::Relaxed)
+    }
+
+    /// 包括[`State::Reset`]和panic之后的重启
+    #[inline]
+    pub fn add_restart(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// panic之后的重启, 计入[`Actor::MAX_RESTARTS`](crate::Actor::MAX_RESTARTS)
+    #[inline]
+    pub fn add_panic_restart(&self) {
+        self.panic_restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn set_phase(&self, _phase: Phase) {
+        #[cfg(feature = "introspect")]
+        self.phase.store(_phase as u8, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn set_state(&self, _state: &State) {
+        #[cfg(feature = "introspect")]
+        self.state
+            .store(StateKind::from(_state) as u8, Ordering::Relaxed);
+    }
+
+    /// 开始或结束处理消息
+    #[inline]
+    pub fn set_current_message(&self, _message_type: Option<&'static str>) {
+        #[cfg(feature = "introspect")]
+        {
+            *self.current_message.lock().unwrap() = _message_type;
+            self.set_phase(if _message_type.is_some() {
+                Phase::Handling
+            } else {
+                Phase::Idle
+            });
+        }
+    }
+
+    #[cfg(feature = "introspect")]
+    fn snapshot(&self) -> ActorSnapshot {
+        ActorSnapshot {
+            id: self.id,
+            phase: PHASES[self.phase.load(Ordering::Relaxed) as usize],
+            state: STATES[self.state.load(Ordering::Relaxed) as usize],
+            restarts: self.restarts(),
+            panic_restarts: self.panic_restarts(),
+            current_message: self
+                .current_message
+                .lock()
+                .unwrap()
+                .map(ToOwned::to_owned),
+        }
+    }
+}
+
+#[cfg(feature = "introspect")]
+const PHASES: [Phase; 6] = [
+    Phase::Creating,
+    Phase::Starting,
+    Phase::Idle,
+    Phase::Handling,
+    Phase::Stopping,
+    Phase::Stopped,
+];
+
+#[cfg(feature = "introspect")]
+const STATES: [StateKind; 7] = [
+    StateKind::Continue,
+    StateKind::Stop,
+    StateKind::Pause,
+    StateKind::Yield,
+    StateKind::Reset,
+    StateKind::Abort,
+    StateKind::Sleep,
+];
+
+#[cfg(all(test, feature = "introspect"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{snapshot, BrokerSnapshot, Phase, KEEP_STOPPED};
+    use crate::{Actor, Broker, Context, MessageHandler};
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    /// 第一个之后的actor在`create`中停止
+    struct Spawner;
+
+    struct Spawn(usize);
+
+    impl Actor for Spawner {
+        const MAIL_BOX_SIZE: u32 = 8;
+        type Args = ();
+
+        async fn create(ctx: &mut Context<Self>) -> Self {
+            if CREATED.fetch_add(1, Ordering::Relaxed) > 0 {
+                ctx.stop();
+            }
+            Spawner
+        }
+    }
+
+    impl MessageHandler<Spawn> for Spawner {
+        type Output = ();
+
+        async fn handle(&mut self, Spawn(n): Spawn, ctx: &mut Context<Self>) {
+            for _ in 0..n {
+                ctx.global().spawn().await.join().await;
+            }
+        }
+    }
+
+    fn find() -> BrokerSnapshot {
+        snapshot()
+            .brokers
+            .into_iter()
+            .find(|broker| broker.actor_type == std::any::type_name::<Spawner>())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stopped_actors_are_bounded() {
+        let broker = Broker::<Spawner>::spawn_one().await;
+        broker.call(Spawn(100)).await.unwrap();
+        let snapshot = find();
+        assert_eq!(snapshot.instances, 1);
+        assert_eq!(snapshot.actors.len(), 1 + KEEP_STOPPED);
+        assert_eq!(snapshot.actors[0].id, 0);
+        assert!(snapshot.actors[1..]
+            .iter()
+            .all(|actor| actor.phase == Phase::Stopped && actor.id > 100 - KEEP_STOPPED));
+    }
+}
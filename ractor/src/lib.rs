@@ -1,26 +1,69 @@
 #![allow(rustdoc::broken_intra_doc_links)]
 
-pub use actor::Actor;
-pub use actor_runner::StoppingPosition;
+pub use actor::{Actor, PanicPayload, RestartReason, Running};
+pub use actor_runner::{ActorExit, StoppingPosition};
+pub use behavior::{Behavior, BehaviorActor, Fallback, Unhandled};
 #[cfg(feature = "remote")]
 pub use address::RemoteAddress;
-pub use address::{Address, LocalAddress};
+pub use address::{Address, CallError, LocalAddress};
+#[cfg(feature = "rt-tokio")]
+pub use arbiter::Arbiter;
 pub use broker::{Broker, SpawnHandle};
 #[cfg(feature = "remote")]
-pub use context::MessageRegister;
+pub use context::{IdentityCollision, MessageRegister};
 pub use context::{Context, GlobalContext, State};
-pub use message::{Message, MessageHandler, ResponseHandle};
-/*#[cfg(feature = "derive")]
-pub use ractor_derive::*;
-*/
+pub use message::{Message, MessageHandler, ResponseHandle, TypedMessage};
+pub use meta::EnvelopeMeta;
+pub use recipient::{Caller, Recipient};
+#[cfg(feature = "rt-tokio")]
+pub use sync_broker::{SyncActor, SyncBroker, SyncHandler};
+#[cfg(feature = "derive")]
+pub use ractor_derive::{async_trait, behavior, handler, handlers, Actor, Behavior, Message};
+#[cfg(all(feature = "derive", feature = "remote"))]
+pub use ractor_derive::RemoteType;
+#[cfg(feature = "remote")]
+pub use ractor_rpc::RemoteType;
 mod actor;
 mod actor_runner;
 mod address;
+#[cfg(feature = "rt-tokio")]
+mod arbiter;
+mod behavior;
+pub mod breaker;
 mod broker;
 mod context;
+mod deferred;
 mod envelope;
 pub mod error;
+mod intake;
+pub mod interceptor;
+pub mod introspect;
+pub mod limiter;
 mod message;
+mod meta;
+mod pause;
+#[cfg(feature = "persistence")]
+pub mod persistence;
+mod recipient;
+mod response;
+pub mod retry;
+pub mod rt;
+#[cfg(feature = "tower")]
+pub mod service;
+mod stash;
+#[cfg(feature = "rt-tokio")]
+mod sync_broker;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+#[cfg(feature = "rt-tokio")]
+pub mod unsend;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+
+    pub use crate::message::{AnyOutput, OutputCheck, TypedOutput};
+}
 
 #[cfg(test)]
 mod tests {}
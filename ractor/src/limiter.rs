@@ -0,0 +1,320 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::address::CallError;
+use crate::envelope::Envelope;
+use crate::error::ChannelSendError;
+use crate::message::Message;
+use crate::{Actor, LocalAddress, MessageHandler, ResponseHandle};
+
+/// 超出限制时的行为
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LimitMode {
+    /// 等待直到有可用的令牌, 允许不超过`burst`的突发
+    Wait,
+    /// 不等待, 直接拒绝
+    ///
+    /// 用于信箱入口时, 超出限制的消息在发送时直接返回`RateLimited`错误, 不会进入信箱.
+    Reject,
+    /// 等待并以固定的间隔放行, 不允许突发(漏桶)
+    Shape,
+}
+
+/// 令牌桶限速器
+///
+/// ```ignore
+/// // 每秒100个, 最多突发10个
+/// let limiter = RateLimiter::per_second(100).with_burst(10);
+/// ```
+pub struct RateLimiter {
+    /// 每个令牌的间隔
+    interval: Duration,
+    burst: u32,
+    mode: LimitMode,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// 可用的令牌数量, 为负数时表示已经预约的令牌
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// 每`per`时间内最多`quantity`个
+    ///
+    /// 默认允许突发`quantity`个, 使用[`LimitMode::Wait`].
+    pub fn new(quantity: u32, per: Duration) -> Self {
+        assert!(quantity > 0, "quantity must be greater than 0");
+        RateLimiter {
+            interval: per / quantity,
+            burst: quantity,
+            mode: LimitMode::Wait,
+            bucket: Mutex::new(Bucket {
+                tokens: quantity as f64,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn per_second(quantity: u32) -> Self {
+        RateLimiter::new(quantity, Duration::from_secs(1))
+    }
+
+    /// 设置最大突发数量, 同时也是桶的容量
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self.bucket.get_mut().unwrap().tokens = self.capacity();
+        self
+    }
+
+    pub fn with_mode(mut self, mode: LimitMode) -> Self {
+        self.mode = mode;
+        self.bucket.get_mut().unwrap().tokens = self.capacity();
+        self
+    }
+
+    #[inline]
+    pub fn mode(&self) -> LimitMode {
+        self.mode
+    }
+
+    #[inline]
+    fn capacity(&self) -> f64 {
+        match self.mode {
+            LimitMode::Shape => 1.0,
+            _ => self.burst as f64,
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.last);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() / self.interval.as_secs_f64())
+            .min(self.capacity());
+        bucket.last = now;
+    }
+
+    /// 获取一个令牌, 不等待
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, Instant::now());
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 获取一个令牌
+    ///
+    /// [`LimitMode::Reject`]时不等待, 没有令牌时返回错误.
+    pub async fn acquire(&self) -> Result<(), RateLimited<()>> {
+        if self.mode == LimitMode::Reject {
+            return if self.try_acquire() {
+                Ok(())
+            } else {
+                Err(RateLimited(()))
+            };
+        }
+
+        // 先预约令牌再等待, 保证先到先得
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            self.refill(&mut bucket, Instant::now());
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                self.interval.mul_f64(-bucket.tokens)
+            }
+        };
+        if !wait.is_zero() {
+            crate::rt::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+/// 超出了限制
+pub struct RateLimited<T>(pub(crate) T);
+
+impl<T> Debug for RateLimited<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The rate limit has been exceeded.")
+    }
+}
+
+impl<T> Display for RateLimited<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<T> std::error::Error for RateLimited<T> {}
+
+impl<T> RateLimited<T> {
+    pub fn recover(self) -> T {
+        self.0
+    }
+}
+
+/// 限制发送速度的地址
+///
+/// 所有clone共享同一个[`RateLimiter`].
+pub struct Throttled<A>
+where
+    A: Actor,
+{
+    addr: LocalAddress<A>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<A> Throttled<A>
+where
+    A: Actor,
+{
+    #[inline]
+    pub fn new(addr: LocalAddress<A>, limiter: RateLimiter) -> Self {
+        Throttled {
+            addr,
+            limiter: Arc::new(limiter),
+        }
+    }
+
+    #[inline]
+    pub fn addr(&self) -> &LocalAddress<A> {
+        &self.addr
+    }
+
+    pub async fn send<M>(
+        &self,
+        msg: M,
+    ) -> Result<
+        ResponseHandle<<A as MessageHandler<M>>::Output>,
+        ThrottleError<M, ChannelSendError<Envelope<A>>>,
+    >
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        if self.limiter.acquire().await.is_err() {
+            return Err(ThrottleError::RateLimited(msg));
+        }
+        self.addr.send(msg).await.map_err(ThrottleError::Error)
+    }
+
+    pub async fn call<M>(
+        &self,
+        msg: M,
+    ) -> Result<<A as MessageHandler<M>>::Output, ThrottleError<M, CallError<A>>>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        if self.limiter.acquire().await.is_err() {
+            return Err(ThrottleError::RateLimited(msg));
+        }
+        self.addr.call(msg).await.map_err(ThrottleError::Error)
+    }
+}
+
+impl<A> Clone for Throttled<A>
+where
+    A: Actor,
+{
+    fn clone(&self) -> Self {
+        Throttled {
+            addr: self.addr.clone(),
+            limiter: Arc::clone(&self.limiter),
+        }
+    }
+}
+
+pub enum ThrottleError<M, E> {
+    /// [`LimitMode::Reject`]时超出了限制, 返回原消息
+    RateLimited(M),
+    Error(E),
+}
+
+impl<M, E> Debug for ThrottleError<M, E>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThrottleError::RateLimited(_) => write!(f, "The rate limit has been exceeded."),
+            ThrottleError::Error(err) => Debug::fmt(err, f),
+        }
+    }
+}
+
+impl<M, E> Display for ThrottleError<M, E>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<M, E> std::error::Error for ThrottleError<M, E> where E: Debug {}
+
+#[cfg(all(test, feature = "rt-tokio"))]
+mod tests {
+    use super::*;
+    use crate::error::ChannelTrySendError;
+    use crate::retry::CallErrorKind;
+    use crate::{Broker, Context};
+
+    struct Echo;
+
+    struct Ping(u32);
+
+    impl Actor for Echo {
+        const MAIL_BOX_SIZE: u32 = 8;
+        type Args = ();
+
+        async fn create(_ctx: &mut Context<Self>) -> Self {
+            Echo
+        }
+    }
+
+    impl MessageHandler<Ping> for Echo {
+        type Output = u32;
+
+        async fn handle(&mut self, Ping(n): Ping, _ctx: &mut Context<Self>) -> u32 {
+            n
+        }
+    }
+
+    #[tokio::test]
+    async fn reject_at_enqueue() {
+        let broker = Broker::<Echo>::spawn(1, false).await;
+        let addr = broker.addr();
+        addr.set_rate_limit(Some(
+            RateLimiter::new(1, Duration::from_secs(3600)).with_mode(LimitMode::Reject),
+        ));
+
+        assert_eq!(addr.call(Ping(1)).await.unwrap(), 1);
+        let err = addr.call(Ping(2)).await.unwrap_err();
+        assert_eq!(err.kind(), CallErrorKind::RateLimited);
+        match addr.try_do_send(Ping(3)) {
+            Err(ChannelTrySendError::RateLimited(_)) => {}
+            _ => panic!("expected RateLimited"),
+        }
+        match addr.recipient::<Ping>().send(Ping(4)).await {
+            Err(ChannelSendError::RateLimited(())) => {}
+            _ => panic!("expected RateLimited"),
+        }
+        // 被拒绝的消息没有进入信箱
+        assert!(addr.sender.is_empty());
+
+        addr.set_rate_limit(None);
+        assert_eq!(addr.call(Ping(5)).await.unwrap(), 5);
+    }
+}
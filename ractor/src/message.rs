@@ -1,22 +1,79 @@
 use std::fmt::{Debug, Formatter, Display};
+use std::future::Future;
+use std::marker::PhantomData;
 
-use async_trait::async_trait;
-
-use crate::actor::Actor;
-use crate::envelope::RespRx;
+use crate::actor::{Actor, UnsendActor};
+use crate::behavior::Unhandled;
+use crate::response::Receiver;
 use crate::Context;
 
 pub trait Message: Send {}
 
 impl<T> Message for T where T: Send {}
 
-#[async_trait]
+/// 声明了响应类型的消息
+///
+/// [`Message`]对所有`Send`的类型都已经实现, 所以响应类型放在单独的trait中,
+/// 一般通过`#[derive(Message)]`和`#[rtype(result = "T")]`实现.
+/// 通过[`Caller`](crate::Caller)和`RemoteAddress::send_typed`发送时,
+/// 处理者的`Output`必须是`Self::Result`, 调用者不需要知道actor的类型.
+///
+/// `#[handlers]`生成的处理者在编译时检查`Output`是否为`Self::Result`.
+/// 手写的`impl MessageHandler<M>`不会被检查, 直到通过[`Caller`](crate::Caller)或`send_typed`发送.
+pub trait TypedMessage: Message {
+    type Result: Send + 'static;
+}
+
+/// `#[handlers]`用来检查`Output`是否为`M::Result`
+///
+/// `M`实现了[`TypedMessage`]时`output`来自[`TypedOutput`], 类型必须一致;
+/// 否则通过自动引用退回到[`AnyOutput`], 不做检查.
+#[doc(hidden)]
+pub struct OutputCheck<M>(pub PhantomData<M>);
+
+#[doc(hidden)]
+pub trait TypedOutput {
+    type Result;
+
+    #[inline]
+    fn output(&self) -> PhantomData<Self::Result> {
+        PhantomData
+    }
+}
+
+impl<M> TypedOutput for OutputCheck<M>
+where
+    M: TypedMessage,
+{
+    type Result = M::Result;
+}
+
+#[doc(hidden)]
+pub trait AnyOutput {
+    #[inline]
+    fn output<O>(&self) -> PhantomData<O> {
+        PhantomData
+    }
+}
+
+impl<M> AnyOutput for &OutputCheck<M> {}
+
+/// 处理消息`M`
+///
+/// 和[`Actor`]一样, 实现时直接写`async fn handle`.
 pub trait MessageHandler<M>: Sized + Send
 where
     Self: Actor,
     M: Message,
 {
     type Output: Send + 'static;
+
+    /// 调用者丢弃[`ResponseHandle`]之后是否取消
+    ///
+    /// 为`true`时, 取出消息时如果已经没有人等待响应, 消息会被跳过.
+    /// 处理期间可以通过[`Context::is_cancelled`]和[`Context::cancelled`]得知.
+    const CANCELLABLE: bool = false;
+
     /// 处理消息
     ///
     /// # Error
@@ -28,12 +85,69 @@ where
     ///
     /// 也就是说`Self::Error`适用于你发送了消息但不需要接收响应的时候处理错误,
     /// 而`Output = Result<..., Error1>`适用于在等待接收响应之后处理错误.
-    async fn handle(&mut self, msg: M, ctx: &mut Context<Self>) -> Self::Output;
+    fn handle(
+        &mut self,
+        msg: M,
+        ctx: &mut Context<Self>,
+    ) -> impl Future<Output = Self::Output> + Send;
+
+    /// 当前行为下不处理该消息时, 返回兜底的处理方式
+    ///
+    /// 默认总是处理. 一般由`#[behavior(...)]`生成, 见[`BehaviorActor`].
+    #[inline]
+    fn unhandled(&self, _ctx: &Context<Self>) -> Option<Unhandled<Self::Output>> {
+        None
+    }
 }
 
-pub struct ResponseHandle<O>(pub(crate) RespRx<O>);
+/// 处理消息`M`, 返回的future不需要`Send`
+///
+/// 和[`UnsendActor`]一样是[`MessageHandler`]的基础, 所有[`MessageHandler`]都自动实现了这个trait.
+/// 各项的含义见[`MessageHandler`]中的同名项.
+pub trait UnsendHandler<M>: UnsendActor + Sized
+where
+    M: Message,
+{
+    type Output: Send + 'static;
+
+    const CANCELLABLE: bool = false;
+
+    fn handle(&mut self, msg: M, ctx: &mut Context<Self>) -> impl Future<Output = Self::Output>;
+
+    #[inline]
+    fn unhandled(&self, _ctx: &Context<Self>) -> Option<Unhandled<Self::Output>> {
+        None
+    }
+}
+
+impl<A, M> UnsendHandler<M> for A
+where
+    A: MessageHandler<M>,
+    M: Message,
+{
+    type Output = <A as MessageHandler<M>>::Output;
 
-impl<O> ResponseHandle<O> {
+    const CANCELLABLE: bool = <A as MessageHandler<M>>::CANCELLABLE;
+
+    #[inline]
+    fn handle(&mut self, msg: M, ctx: &mut Context<Self>) -> impl Future<Output = Self::Output> {
+        <A as MessageHandler<M>>::handle(self, msg, ctx)
+    }
+
+    #[inline]
+    fn unhandled(&self, ctx: &Context<Self>) -> Option<Unhandled<Self::Output>> {
+        <A as MessageHandler<M>>::unhandled(self, ctx)
+    }
+}
+
+pub struct ResponseHandle<O>(pub(crate) Receiver<O>)
+where
+    O: Send + 'static;
+
+impl<O> ResponseHandle<O>
+where
+    O: Send + 'static,
+{
     #[inline]
     pub async fn recv(self) -> Result<O, HandlerPanic> {
         self.0.await.map_err(|_| HandlerPanic)
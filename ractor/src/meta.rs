@@ -0,0 +1,118 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use tokio::time::Instant;
+
+use crate::message::Message;
+use crate::recipient::Recipient;
+
+/// 消息附带的元数据
+///
+/// 通过[`LocalAddress::send_with`](crate::LocalAddress::send_with)发送,
+/// 处理消息时由[`Context::envelope_meta`](crate::Context::envelope_meta)读取.
+///
+/// ```ignore
+/// let meta = EnvelopeMeta::new()
+///     .with_sender(reply_to.recipient::<Pong>())
+///     .with_correlation_id("req-1")
+///     .with_header("trace-id", "abc");
+/// addr.send_with(Ping, meta).await?;
+/// ```
+#[derive(Clone)]
+pub struct EnvelopeMeta {
+    sender: Option<Arc<dyn Any + Send + Sync>>,
+    correlation_id: Option<String>,
+    sent_at: Instant,
+    headers: HashMap<String, String>,
+}
+
+impl EnvelopeMeta {
+    #[inline]
+    pub fn new() -> Self {
+        EnvelopeMeta {
+            sender: None,
+            correlation_id: None,
+            sent_at: Instant::now(),
+            headers: HashMap::new(),
+        }
+    }
+
+    /// 用于回复的地址, 见[`EnvelopeMeta::sender`]
+    #[inline]
+    pub fn with_sender<M>(mut self, sender: Recipient<M>) -> Self
+    where
+        M: Message + 'static,
+    {
+        self.sender = Some(Arc::new(sender));
+        self
+    }
+
+    #[inline]
+    pub fn with_correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    #[inline]
+    pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    /// 发送者的地址, 不存在或者消息类型不是`M`时为`None`
+    #[inline]
+    pub fn sender<M>(&self) -> Option<Recipient<M>>
+    where
+        M: Message + 'static,
+    {
+        self.sender.as_ref()?.downcast_ref::<Recipient<M>>().cloned()
+    }
+
+    #[inline]
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// 发送的时间
+    #[inline]
+    pub fn sent_at(&self) -> Instant {
+        self.sent_at
+    }
+
+    #[inline]
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers.get(key).map(String::as_str)
+    }
+
+    #[inline]
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    /// 发送时更新时间
+    #[inline]
+    pub(crate) fn stamp(mut self) -> Box<Self> {
+        self.sent_at = Instant::now();
+        Box::new(self)
+    }
+}
+
+impl Default for EnvelopeMeta {
+    #[inline]
+    fn default() -> Self {
+        EnvelopeMeta::new()
+    }
+}
+
+impl Debug for EnvelopeMeta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnvelopeMeta")
+            .field("sender", &self.sender.is_some())
+            .field("correlation_id", &self.correlation_id)
+            .field("sent_at", &self.sent_at)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
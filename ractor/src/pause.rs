@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+/// 控制同一个地址的全部actor是否从信箱中取出消息
+#[derive(Default)]
+pub(crate) struct PauseGate {
+    paused: AtomicBool,
+    notify: Notify,
+}
+
+impl PauseGate {
+    #[inline]
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+        self.notify.notify_waiters();
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// 等待直到没有暂停
+    pub async fn wait(&self) {
+        while self.is_paused() {
+            // `Notified`在创建之后就能收到`notify_waiters`的通知
+            let notified = self.notify.notified();
+            if !self.is_paused() {
+                break;
+            }
+            notified.await;
+        }
+    }
+}
@@ -0,0 +1,898 @@
+//! 事件溯源的持久化actor
+//!
+//! [`PersistentActor`]在处理消息时通过[`PersistentActor::persist`]把事件写入[`Journal`],
+//! 创建或重置时通过[`PersistentActor::recover`]读取快照并重放之后的事件来恢复状态.
+//!
+//! 每个`persistence_id`只能有一个actor写入. [`Broker`](crate::Broker)中有多个actor时,
+//! 它们的`persistence_id`必须各不相同, 否则[`Journal::append`]会返回[`JournalError::Conflict`].
+//! 一般把[`Context::id`]拼进`persistence_id`, 它在重启和重新生成broker之后保持不变.
+//!
+//! 保存快照之后默认删除快照之前的事件([`PersistentActor::DELETE_EVENTS_ON_SNAPSHOT`]),
+//! 所以日志的大小和恢复时读取的数据量都不会超过快照间隔.
+//!
+//! ```ignore
+//! impl Actor for Counter {
+//!     const MAIL_BOX_SIZE: u32 = 10;
+//!     type Args = Arc<MemoryJournal>;
+//!
+//!     async fn create(ctx: &mut Context<Self>) -> Self {
+//!         Self::recover(ctx).await.expect("failed to recover")
+//!     }
+//! }
+//!
+//! impl PersistentActor for Counter {
+//!     fn persistence_id(ctx: &Context<Self>) -> String {
+//!         format!("counter-{}", ctx.id())
+//!     }
+//!     // ...
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+#[cfg(feature = "rt-tokio")]
+use std::io::ErrorKind;
+#[cfg(feature = "rt-tokio")]
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+#[cfg(feature = "rt-tokio")]
+use tokio::io::AsyncWriteExt;
+
+use crate::{Actor, Context};
+
+#[derive(Debug, Error)]
+pub enum JournalError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("de/serialize error: {0}")]
+    Bincode(#[from] bincode::Error),
+
+    /// 序号不连续, 通常是有多个actor写入同一个`persistence_id`
+    #[error("sequence conflict: expected {expected}, found {found}")]
+    Conflict { expected: u64, found: u64 },
+}
+
+/// 日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// 序号, 从1开始
+    pub seq: u64,
+    pub payload: Vec<u8>,
+}
+
+/// 保存事件和快照
+///
+/// 同一个`persistence_id`的记录序号从1开始连续递增.
+#[async_trait::async_trait]
+pub trait Journal: Send + Sync + 'static {
+    /// 追加事件
+    ///
+    /// `record.seq`不是上一条事件的序号加1时返回[`JournalError::Conflict`], 不写入.
+    async fn append(&self, persistence_id: &str, record: Record) -> Result<(), JournalError>;
+
+    /// 按顺序读取序号大于`after`的事件
+    async fn read(&self, persistence_id: &str, after: u64) -> Result<Vec<Record>, JournalError>;
+
+    /// 保存快照, 替换之前的快照
+    async fn save_snapshot(
+        &self,
+        persistence_id: &str,
+        snapshot: Record,
+    ) -> Result<(), JournalError>;
+
+    async fn load_snapshot(&self, persistence_id: &str) -> Result<Option<Record>, JournalError>;
+
+    /// 删除序号小于`seq`的事件
+    ///
+    /// 序号为`seq`的事件会保留, 之后的追加仍然从它开始检查序号.
+    async fn delete_before(&self, persistence_id: &str, seq: u64) -> Result<(), JournalError>;
+}
+
+pub trait PersistentActor: Actor + Sized {
+    type Event: Serialize + DeserializeOwned + Send + Sync;
+    type Snapshot: Serialize + DeserializeOwned + Send;
+
+    /// 每持久化多少个事件保存一次快照, 为0时不保存快照
+    const SNAPSHOT_INTERVAL: u64 = 0;
+
+    /// 保存快照之后是否删除快照之前的事件, 为`false`时保留完整的事件历史
+    const DELETE_EVENTS_ON_SNAPSHOT: bool = true;
+
+    /// 同一个broker中的actor应该各不相同, 见[`Context::id`]
+    fn persistence_id(ctx: &Context<Self>) -> String;
+
+    fn journal(ctx: &Context<Self>) -> Arc<dyn Journal>;
+
+    /// 没有任何快照和事件时的初始状态
+    fn initial(ctx: &Context<Self>) -> Self;
+
+    /// 把事件应用到状态上
+    ///
+    /// 持久化和重放时都会调用, 不应该有副作用.
+    fn apply(&mut self, event: &Self::Event);
+
+    fn snapshot(&self) -> Self::Snapshot;
+
+    fn from_snapshot(snapshot: Self::Snapshot, ctx: &Context<Self>) -> Self;
+
+    /// 写入事件并应用到状态上
+    ///
+    /// 写入事件失败时状态不会改变. 事件写入之后保存快照失败只记录日志, 不影响结果,
+    /// 恢复时会从上一个快照开始重放.
+    fn persist(
+        &mut self,
+        event: Self::Event,
+        ctx: &mut Context<Self>,
+    ) -> impl Future<Output = Result<(), JournalError>> + Send {
+        async move {
+            let journal = Self::journal(ctx);
+            let id = Self::persistence_id(ctx);
+            let seq = ctx.persisted_seq + 1;
+
+            journal
+                .append(
+                    &id,
+                    Record {
+                        seq,
+                        payload: serialize(&event)?,
+                    },
+                )
+                .await?;
+            self.apply(&event);
+            ctx.persisted_seq = seq;
+
+            if seq.checked_rem(Self::SNAPSHOT_INTERVAL) == Some(0) {
+                let result = match serialize(&self.snapshot()) {
+                    Ok(payload) => journal.save_snapshot(&id, Record { seq, payload }).await,
+                    Err(err) => Err(err.into()),
+                };
+                match result {
+                    Ok(()) if Self::DELETE_EVENTS_ON_SNAPSHOT => {
+                        if let Err(err) = journal.delete_before(&id, seq).await {
+                            log::warn!(
+                                "failed to delete the events of `{}` before {}: {}",
+                                id,
+                                seq,
+                                err
+                            );
+                        }
+                    }
+                    Ok(()) => {}
+                    Err(err) => {
+                        log::warn!(
+                            "failed to save the snapshot of `{}` at {}: {}",
+                            id,
+                            seq,
+                            err
+                        )
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// 读取最近的快照并重放之后的事件
+    fn recover(ctx: &mut Context<Self>) -> impl Future<Output = Result<Self, JournalError>> + Send {
+        async move {
+            let journal = Self::journal(ctx);
+            let id = Self::persistence_id(ctx);
+
+            let (mut actor, mut seq) = match journal.load_snapshot(&id).await? {
+                Some(record) => (
+                    Self::from_snapshot(deserialize(&record.payload)?, ctx),
+                    record.seq,
+                ),
+                None => (Self::initial(ctx), 0),
+            };
+            for record in journal.read(&id, seq).await? {
+                actor.apply(&deserialize(&record.payload)?);
+                seq = record.seq;
+            }
+            ctx.persisted_seq = seq;
+            Ok(actor)
+        }
+    }
+}
+
+/// 事件和快照
+type Entry = (Vec<Record>, Option<Record>);
+
+/// 保存在内存中的[`Journal`]
+#[derive(Default)]
+pub struct MemoryJournal {
+    inner: Mutex<HashMap<String, Entry>>,
+}
+
+impl MemoryJournal {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl Journal for MemoryJournal {
+    async fn append(&self, persistence_id: &str, record: Record) -> Result<(), JournalError> {
+        let mut inner = self.inner.lock().unwrap();
+        let records = &mut inner.entry(persistence_id.to_owned()).or_default().0;
+        check_seq(records.last().map_or(0, |last| last.seq), record.seq)?;
+        records.push(record);
+        Ok(())
+    }
+
+    async fn read(&self, persistence_id: &str, after: u64) -> Result<Vec<Record>, JournalError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .get(persistence_id)
+            .map(|(records, _)| {
+                records
+                    .iter()
+                    .filter(|record| record.seq > after)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn save_snapshot(
+        &self,
+        persistence_id: &str,
+        snapshot: Record,
+    ) -> Result<(), JournalError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entry(persistence_id.to_owned()).or_default().1 = Some(snapshot);
+        Ok(())
+    }
+
+    async fn load_snapshot(&self, persistence_id: &str) -> Result<Option<Record>, JournalError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .get(persistence_id)
+            .and_then(|(_, snapshot)| snapshot.clone()))
+    }
+
+    async fn delete_before(&self, persistence_id: &str, seq: u64) -> Result<(), JournalError> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((records, _)) = inner.get_mut(persistence_id) {
+            records.retain(|record| record.seq >= seq);
+        }
+        Ok(())
+    }
+}
+
+/// 只追加写入的文件[`Journal`]
+///
+/// 每个`persistence_id`对应目录下的`{id}.journal`和`{id}.snapshot`两个文件,
+/// 所以`persistence_id`应该是合法的文件名.
+///
+/// 每条记录的格式为`seq(u64 le) | len(u32 le) | crc32(u32 le) | payload`, 校验和包括前两项和`payload`.
+/// 第一次读写某个`persistence_id`时, 末尾不完整或校验失败的记录(例如写入时进程退出)会被截断.
+/// 追加写入的文件句柄会一直保持打开, [`Journal::delete_before`]把剩下的记录写入新文件之后替换原来的日志.
+///
+/// 通过`tokio::fs`读写, 需要`rt-tokio` feature.
+#[cfg(feature = "rt-tokio")]
+pub struct FileJournal {
+    dir: PathBuf,
+    /// 每次写入之后是否调用`fsync`
+    sync: bool,
+    journals: Mutex<HashMap<String, Arc<tokio::sync::Mutex<JournalFile>>>>,
+}
+
+/// 单个日志的状态
+#[cfg(feature = "rt-tokio")]
+#[derive(Default)]
+struct JournalFile {
+    /// 最后的序号, 为`None`时需要重新检查文件
+    last_seq: Option<u64>,
+    /// 追加写入的文件, 第一次写入时打开
+    file: Option<tokio::fs::File>,
+}
+
+#[cfg(feature = "rt-tokio")]
+impl FileJournal {
+    pub async fn open(dir: impl Into<PathBuf>) -> Result<Self, JournalError> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(&dir).await?;
+        Ok(FileJournal {
+            dir,
+            sync: false,
+            journals: Default::default(),
+        })
+    }
+
+    #[inline]
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    #[inline]
+    fn path(&self, persistence_id: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", persistence_id, ext))
+    }
+
+    fn journal(&self, persistence_id: &str) -> Arc<tokio::sync::Mutex<JournalFile>> {
+        let mut journals = self.journals.lock().unwrap();
+        Arc::clone(journals.entry(persistence_id.to_owned()).or_default())
+    }
+
+    /// 读取整个日志文件, 截断末尾损坏的记录
+    async fn load(&self, persistence_id: &str) -> Result<Vec<u8>, JournalError> {
+        let path = self.path(persistence_id, "journal");
+        let mut bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let valid = RawRecords::new(&bytes).last().map_or(0, |raw| raw.end);
+        if valid < bytes.len() {
+            log::warn!(
+                "truncate {} bytes of torn records at the end of `{}`",
+                bytes.len() - valid,
+                path.display()
+            );
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .await?;
+            file.set_len(valid as u64).await?;
+            if self.sync {
+                file.sync_data().await?;
+            }
+            bytes.truncate(valid);
+        }
+        Ok(bytes)
+    }
+
+    /// 写入临时文件`tmp`之后重命名为`path`
+    async fn replace(&self, tmp: &Path, path: &Path, bytes: &[u8]) -> Result<(), JournalError> {
+        let mut file = tokio::fs::File::create(tmp).await?;
+        file.write_all(bytes).await?;
+        file.flush().await?;
+        if self.sync {
+            file.sync_data().await?;
+        }
+        drop(file);
+        tokio::fs::rename(tmp, path).await?;
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        journal: &mut JournalFile,
+        persistence_id: &str,
+        record: &Record,
+    ) -> Result<(), JournalError> {
+        let file = match &mut journal.file {
+            Some(file) => file,
+            None => journal.file.insert(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.path(persistence_id, "journal"))
+                    .await?,
+            ),
+        };
+        file.write_all(&encode(record)).await?;
+        file.flush().await?;
+        if self.sync {
+            file.sync_data().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rt-tokio")]
+#[async_trait::async_trait]
+impl Journal for FileJournal {
+    async fn append(&self, persistence_id: &str, record: Record) -> Result<(), JournalError> {
+        let journal = self.journal(persistence_id);
+        let mut journal = journal.lock().await;
+        let last = match journal.last_seq {
+            Some(last) => last,
+            None => {
+                let bytes = self.load(persistence_id).await?;
+                RawRecords::new(&bytes).last().map_or(0, |raw| raw.seq)
+            }
+        };
+        journal.last_seq = Some(last);
+        check_seq(last, record.seq)?;
+        match self.write(&mut journal, persistence_id, &record).await {
+            Ok(()) => {
+                journal.last_seq = Some(record.seq);
+                Ok(())
+            }
+            Err(err) => {
+                // 可能写入了一部分, 下次访问时截断
+                *journal = JournalFile::default();
+                Err(err)
+            }
+        }
+    }
+
+    async fn read(&self, persistence_id: &str, after: u64) -> Result<Vec<Record>, JournalError> {
+        let journal = self.journal(persistence_id);
+        let mut journal = journal.lock().await;
+        let bytes = self.load(persistence_id).await?;
+        let mut last = 0;
+        let mut records = Vec::new();
+        // 只复制`after`之后的payload
+        for raw in RawRecords::new(&bytes) {
+            last = raw.seq;
+            if raw.seq > after {
+                records.push(Record {
+                    seq: raw.seq,
+                    payload: raw.payload.to_vec(),
+                });
+            }
+        }
+        journal.last_seq = Some(last);
+        Ok(records)
+    }
+
+    async fn save_snapshot(
+        &self,
+        persistence_id: &str,
+        snapshot: Record,
+    ) -> Result<(), JournalError> {
+        // 先写入临时文件再重命名, 避免留下不完整的快照
+        self.replace(
+            &self.path(persistence_id, "snapshot.tmp"),
+            &self.path(persistence_id, "snapshot"),
+            &encode(&snapshot),
+        )
+        .await
+    }
+
+    async fn load_snapshot(&self, persistence_id: &str) -> Result<Option<Record>, JournalError> {
+        match tokio::fs::read(self.path(persistence_id, "snapshot")).await {
+            Ok(bytes) => Ok(RawRecords::new(&bytes).last().map(|raw| Record {
+                seq: raw.seq,
+                payload: raw.payload.to_vec(),
+            })),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete_before(&self, persistence_id: &str, seq: u64) -> Result<(), JournalError> {
+        let journal = self.journal(persistence_id);
+        let mut journal = journal.lock().await;
+        let bytes = self.load(persistence_id).await?;
+        let mut start = None;
+        let mut last = 0;
+        for raw in RawRecords::new(&bytes) {
+            if raw.seq >= seq && start.is_none() {
+                start = Some(raw.start);
+            }
+            last = raw.seq;
+        }
+        journal.last_seq = Some(last);
+        // 没有序号不小于`seq`的事件时不删除, 避免丢失最后的序号
+        let start = match start {
+            Some(start) if start > 0 => start,
+            _ => return Ok(()),
+        };
+        // 原来的句柄指向被替换的文件, 之后重新打开
+        journal.file = None;
+        if let Err(err) = self
+            .replace(
+                &self.path(persistence_id, "journal.tmp"),
+                &self.path(persistence_id, "journal"),
+                &bytes[start..],
+            )
+            .await
+        {
+            journal.last_seq = None;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+/// 序号必须紧接着`last`
+#[inline]
+fn check_seq(last: u64, seq: u64) -> Result<(), JournalError> {
+    if seq == last + 1 {
+        Ok(())
+    } else {
+        Err(JournalError::Conflict {
+            expected: last + 1,
+            found: seq,
+        })
+    }
+}
+
+#[cfg(feature = "rt-tokio")]
+const HEADER_LEN: usize = 16;
+
+#[cfg(feature = "rt-tokio")]
+fn encode(record: &Record) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + record.payload.len());
+    buf.extend_from_slice(&record.seq.to_le_bytes());
+    buf.extend_from_slice(&(record.payload.len() as u32).to_le_bytes());
+    let crc = crc32(&[&buf, &record.payload]);
+    buf.extend_from_slice(&crc.to_le_bytes());
+    buf.extend_from_slice(&record.payload);
+    buf
+}
+
+/// 未解码的记录
+#[cfg(feature = "rt-tokio")]
+struct RawRecord<'a> {
+    seq: u64,
+    payload: &'a [u8],
+    /// 在文件中的起止位置
+    start: usize,
+    end: usize,
+}
+
+/// 依次遍历完整的记录, 遇到不完整或校验失败的记录时结束
+#[cfg(feature = "rt-tokio")]
+struct RawRecords<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+#[cfg(feature = "rt-tokio")]
+impl<'a> RawRecords<'a> {
+    #[inline]
+    fn new(bytes: &'a [u8]) -> Self {
+        RawRecords { bytes, offset: 0 }
+    }
+}
+
+#[cfg(feature = "rt-tokio")]
+impl<'a> Iterator for RawRecords<'a> {
+    type Item = RawRecord<'a>;
+
+    fn next(&mut self) -> Option<RawRecord<'a>> {
+        if self.bytes.len() - self.offset < HEADER_LEN {
+            return None;
+        }
+        let (header, rest) = self.bytes[self.offset..].split_at(HEADER_LEN);
+        let seq = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let len = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[12..].try_into().unwrap());
+        if rest.len() < len || crc32(&[&header[..12], &rest[..len]]) != crc {
+            // 之后的记录都不再可信
+            self.offset = self.bytes.len();
+            return None;
+        }
+        let start = self.offset;
+        self.offset += HEADER_LEN + len;
+        Some(RawRecord {
+            seq,
+            payload: &rest[..len],
+            start,
+            end: self.offset,
+        })
+    }
+}
+
+/// CRC-32 (IEEE)
+#[cfg(feature = "rt-tokio")]
+fn crc32(parts: &[&[u8]]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+    let mut crc = !0u32;
+    for part in parts {
+        for &byte in *part {
+            crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+    }
+    !crc
+}
+
+#[inline]
+fn serialize<T>(data: &T) -> Result<Vec<u8>, bincode::Error>
+where
+    T: Serialize,
+{
+    bincode::DefaultOptions::default()
+        .with_little_endian()
+        .serialize(data)
+}
+
+#[inline]
+fn deserialize<T>(data: &[u8]) -> Result<T, bincode::Error>
+where
+    T: DeserializeOwned,
+{
+    bincode::DefaultOptions::default()
+        .with_little_endian()
+        .deserialize(data)
+}
+
+#[cfg(all(test, feature = "rt-tokio"))]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{Actor, Broker, Context, MessageHandler};
+
+    fn record(seq: u64, payload: &[u8]) -> Record {
+        Record {
+            seq,
+            payload: payload.to_vec(),
+        }
+    }
+
+    /// 每个测试使用单独的空目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ractor-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+    }
+
+    #[tokio::test]
+    async fn append_and_read() {
+        let dir = temp_dir("append");
+        let journal = FileJournal::open(&dir).await.unwrap();
+        for seq in 1..=3 {
+            journal
+                .append("a", record(seq, &[seq as u8]))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            journal.read("a", 1).await.unwrap(),
+            vec![record(2, &[2]), record(3, &[3])]
+        );
+        assert!(journal.read("b", 0).await.unwrap().is_empty());
+
+        let reopened = FileJournal::open(&dir).await.unwrap();
+        assert_eq!(reopened.read("a", 0).await.unwrap().len(), 3);
+        reopened.append("a", record(4, &[4])).await.unwrap();
+        assert_eq!(reopened.read("a", 3).await.unwrap(), vec![record(4, &[4])]);
+    }
+
+    #[tokio::test]
+    async fn append_rejects_conflicting_seq() {
+        let dir = temp_dir("conflict");
+        let file = FileJournal::open(&dir).await.unwrap();
+        let memory = MemoryJournal::new();
+        let journals: [&dyn Journal; 2] = [&file, &memory];
+        for journal in journals {
+            journal.append("a", record(1, b"x")).await.unwrap();
+            let err = journal.append("a", record(1, b"y")).await.unwrap_err();
+            assert!(matches!(
+                err,
+                JournalError::Conflict {
+                    expected: 2,
+                    found: 1
+                }
+            ));
+            assert_eq!(journal.read("a", 0).await.unwrap(), vec![record(1, b"x")]);
+        }
+    }
+
+    #[tokio::test]
+    async fn torn_tail_is_truncated() {
+        let dir = temp_dir("torn");
+        let journal = FileJournal::open(&dir).await.unwrap();
+        journal.append("a", record(1, b"one")).await.unwrap();
+        journal.append("a", record(2, b"two")).await.unwrap();
+
+        let path = dir.join("a.journal");
+        let valid = std::fs::metadata(&path).unwrap().len();
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(&encode(&record(3, b"three"))[..HEADER_LEN + 2]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let reopened = FileJournal::open(&dir).await.unwrap();
+        assert_eq!(reopened.read("a", 0).await.unwrap().len(), 2);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), valid);
+        reopened.append("a", record(3, b"three")).await.unwrap();
+
+        let reopened = FileJournal::open(&dir).await.unwrap();
+        assert_eq!(
+            reopened.read("a", 2).await.unwrap(),
+            vec![record(3, b"three")]
+        );
+    }
+
+    #[tokio::test]
+    async fn corrupted_record_is_truncated() {
+        let dir = temp_dir("corrupted");
+        let journal = FileJournal::open(&dir).await.unwrap();
+        journal.append("a", record(1, b"one")).await.unwrap();
+        journal.append("a", record(2, b"two")).await.unwrap();
+
+        let path = dir.join("a.journal");
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        // 追加之前也会检查
+        let reopened = FileJournal::open(&dir).await.unwrap();
+        let err = reopened.append("a", record(3, b"three")).await.unwrap_err();
+        assert!(matches!(err, JournalError::Conflict { expected: 2, .. }));
+        reopened.append("a", record(2, b"two")).await.unwrap();
+        assert_eq!(reopened.read("a", 0).await.unwrap().len(), 2);
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Added(u64);
+
+    struct Counter {
+        total: u64,
+    }
+
+    struct Add(u64);
+    struct Get;
+
+    impl Actor for Counter {
+        const MAIL_BOX_SIZE: u32 = 8;
+        type Args = Arc<FileJournal>;
+
+        async fn create(ctx: &mut Context<Self>) -> Self {
+            Self::recover(ctx).await.expect("failed to recover")
+        }
+    }
+
+    impl PersistentActor for Counter {
+        type Event = Added;
+        type Snapshot = u64;
+
+        const SNAPSHOT_INTERVAL: u64 = 2;
+
+        fn persistence_id(ctx: &Context<Self>) -> String {
+            format!("counter-{}", ctx.id())
+        }
+
+        fn journal(ctx: &Context<Self>) -> Arc<dyn Journal> {
+            ctx.create_args.clone()
+        }
+
+        fn initial(_ctx: &Context<Self>) -> Self {
+            Counter { total: 0 }
+        }
+
+        fn apply(&mut self, Added(n): &Added) {
+            self.total += n;
+        }
+
+        fn snapshot(&self) -> u64 {
+            self.total
+        }
+
+        fn from_snapshot(total: u64, _ctx: &Context<Self>) -> Self {
+            Counter { total }
+        }
+    }
+
+    impl MessageHandler<Add> for Counter {
+        type Output = Result<u64, String>;
+
+        async fn handle(&mut self, Add(n): Add, ctx: &mut Context<Self>) -> Self::Output {
+            self.persist(Added(n), ctx)
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok(self.total)
+        }
+    }
+
+    impl MessageHandler<Get> for Counter {
+        type Output = u64;
+
+        async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> u64 {
+            self.total
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_before_compacts_journal() {
+        let dir = temp_dir("compact");
+        let journal = FileJournal::open(&dir).await.unwrap();
+        for seq in 1..=4 {
+            journal
+                .append("a", record(seq, &[seq as u8]))
+                .await
+                .unwrap();
+        }
+        journal.delete_before("a", 3).await.unwrap();
+        assert_eq!(
+            std::fs::metadata(dir.join("a.journal")).unwrap().len(),
+            2 * (HEADER_LEN as u64 + 1)
+        );
+        assert_eq!(
+            journal.read("a", 0).await.unwrap(),
+            vec![record(3, &[3]), record(4, &[4])]
+        );
+
+        // 句柄重新打开, 写入替换之后的文件
+        journal.append("a", record(5, &[5])).await.unwrap();
+        let err = journal.append("a", record(2, &[2])).await.unwrap_err();
+        assert!(matches!(err, JournalError::Conflict { expected: 6, .. }));
+
+        let reopened = FileJournal::open(&dir).await.unwrap();
+        assert_eq!(reopened.read("a", 4).await.unwrap(), vec![record(5, &[5])]);
+        reopened.delete_before("a", 9).await.unwrap();
+        reopened.append("a", record(6, &[6])).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn recover_from_snapshot_and_events() {
+        let dir = temp_dir("recover");
+        let journal = Arc::new(FileJournal::open(&dir).await.unwrap());
+
+        let broker = Broker::<Counter>::spawn_with_args(1, false, journal.clone()).await;
+        for n in 1..=3 {
+            broker.call(Add(n)).await.unwrap().unwrap();
+        }
+        broker.wait_for_actors().await;
+        assert_eq!(
+            journal
+                .load_snapshot("counter-0")
+                .await
+                .unwrap()
+                .unwrap()
+                .seq,
+            2
+        );
+        // 快照之前的事件已经删除
+        assert_eq!(journal.read("counter-0", 0).await.unwrap().len(), 2);
+
+        let journal = Arc::new(FileJournal::open(&dir).await.unwrap());
+        let broker = Broker::<Counter>::spawn_with_args(1, false, journal).await;
+        assert_eq!(broker.call(Get).await.unwrap(), 6);
+        assert_eq!(broker.call(Add(4)).await.unwrap(), Ok(10));
+    }
+
+    #[tokio::test]
+    async fn actors_in_broker_have_own_journal() {
+        let dir = temp_dir("broker");
+        let journal = Arc::new(FileJournal::open(&dir).await.unwrap());
+
+        let broker = Broker::<Counter>::spawn_with_args(2, false, journal.clone()).await;
+        for n in 1..=4 {
+            broker.call(Add(n)).await.unwrap().unwrap();
+        }
+        broker.wait_for_actors().await;
+
+        // 两个actor各自恢复出自己的部分, 加起来是全部的事件
+        let mut total = 0;
+        for id in ["counter-0", "counter-1"] {
+            let snapshot = journal.load_snapshot(id).await.unwrap();
+            let after = snapshot.as_ref().map_or(0, |snapshot| snapshot.seq);
+            total += snapshot.map_or(0, |snapshot| deserialize::<u64>(&snapshot.payload).unwrap());
+            for record in journal.read(id, after).await.unwrap() {
+                total += deserialize::<Added>(&record.payload).unwrap().0;
+            }
+        }
+        assert_eq!(total, 10);
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::envelope;
+use crate::error::{ChannelSendError, ChannelTrySendError};
+use crate::message::{Message, MessageHandler, TypedMessage};
+use crate::meta::EnvelopeMeta;
+use crate::{LocalAddress, ResponseHandle};
+
+/// 只能发送`M`的地址, 不关心actor的类型
+///
+/// 由[`LocalAddress::recipient`]得到, 响应会被丢弃.
+/// 失败时无法取回消息, 错误中只有`()`.
+pub struct Recipient<M>
+where
+    M: Message + 'static,
+{
+    sender: Arc<dyn Sender<M>>,
+}
+
+impl<M> Recipient<M>
+where
+    M: Message + 'static,
+{
+    #[inline]
+    pub async fn send(&self, msg: M) -> Result<(), ChannelSendError<()>> {
+        self.sender.send(msg, None).await
+    }
+
+    /// 附带元数据发送, 见[`EnvelopeMeta`]
+    #[inline]
+    pub async fn send_with(&self, msg: M, meta: EnvelopeMeta) -> Result<(), ChannelSendError<()>> {
+        self.sender.send(msg, Some(meta.stamp())).await
+    }
+
+    #[inline]
+    pub fn try_send(&self, msg: M) -> Result<(), ChannelTrySendError<()>> {
+        self.sender.try_send(msg, None)
+    }
+}
+
+impl<M> Clone for Recipient<M>
+where
+    M: Message + 'static,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Recipient {
+            sender: Arc::clone(&self.sender),
+        }
+    }
+}
+
+impl<A, M> From<LocalAddress<A>> for Recipient<M>
+where
+    A: MessageHandler<M>,
+    M: Message + 'static,
+{
+    #[inline]
+    fn from(addr: LocalAddress<A>) -> Self {
+        Recipient {
+            sender: Arc::new(addr),
+        }
+    }
+}
+
+/// 只能发送`M`并接收`M::Result`的地址, 不关心actor的类型
+///
+/// 由[`LocalAddress::caller`]得到, 要求actor处理`M`的`Output`是[`TypedMessage::Result`].
+/// 失败时无法取回消息, 错误中只有`()`.
+pub struct Caller<M>
+where
+    M: TypedMessage + 'static,
+{
+    sender: Arc<dyn TypedSender<M>>,
+}
+
+impl<M> Caller<M>
+where
+    M: TypedMessage + 'static,
+{
+    #[inline]
+    pub async fn send(&self, msg: M) -> Result<ResponseHandle<M::Result>, ChannelSendError<()>> {
+        self.sender.send(msg, None).await
+    }
+
+    /// 附带元数据发送, 见[`EnvelopeMeta`]
+    #[inline]
+    pub async fn send_with(
+        &self,
+        msg: M,
+        meta: EnvelopeMeta,
+    ) -> Result<ResponseHandle<M::Result>, ChannelSendError<()>> {
+        self.sender.send(msg, Some(meta.stamp())).await
+    }
+
+    #[inline]
+    pub fn try_send(&self, msg: M) -> Result<ResponseHandle<M::Result>, ChannelTrySendError<()>> {
+        self.sender.try_send(msg, None)
+    }
+
+    /// 不需要响应时转换为[`Recipient`]
+    #[inline]
+    pub fn recipient(&self) -> Recipient<M> {
+        self.sender.recipient()
+    }
+}
+
+impl<M> Clone for Caller<M>
+where
+    M: TypedMessage + 'static,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Caller {
+            sender: Arc::clone(&self.sender),
+        }
+    }
+}
+
+impl<A, M> From<LocalAddress<A>> for Caller<M>
+where
+    A: MessageHandler<M, Output = M::Result>,
+    M: TypedMessage + 'static,
+{
+    #[inline]
+    fn from(addr: LocalAddress<A>) -> Self {
+        Caller {
+            sender: Arc::new(addr),
+        }
+    }
+}
+
+trait Sender<M>: Send + Sync {
+    fn send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> BoxFuture<'_, Result<(), ChannelSendError<()>>>;
+
+    fn try_send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> Result<(), ChannelTrySendError<()>>;
+}
+
+impl<A, M> Sender<M> for LocalAddress<A>
+where
+    A: MessageHandler<M>,
+    M: Message + 'static,
+{
+    fn send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> BoxFuture<'_, Result<(), ChannelSendError<()>>> {
+        let envelope = envelope::pack_detached::<A, M>(msg).with_meta(meta);
+        async move {
+            if !self.intake.try_enter() {
+                return Err(ChannelSendError::RateLimited(()));
+            }
+            self.sender
+                .send(envelope)
+                .await
+                .map_err(|_| ChannelSendError::Disconnected(()))
+        }
+        .boxed()
+    }
+
+    fn try_send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> Result<(), ChannelTrySendError<()>> {
+        if !self.intake.try_enter() {
+            return Err(ChannelTrySendError::RateLimited(()));
+        }
+        let envelope = envelope::pack_detached::<A, M>(msg).with_meta(meta);
+        self.sender
+            .try_send(envelope)
+            .map_err(|err| ChannelTrySendError::from(err).map(drop))
+    }
+}
+
+trait TypedSender<M>: Send + Sync
+where
+    M: TypedMessage,
+{
+    fn send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> BoxFuture<'_, Result<ResponseHandle<M::Result>, ChannelSendError<()>>>;
+
+    fn try_send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> Result<ResponseHandle<M::Result>, ChannelTrySendError<()>>;
+
+    fn recipient(&self) -> Recipient<M>;
+}
+
+impl<A, M> TypedSender<M> for LocalAddress<A>
+where
+    A: MessageHandler<M, Output = M::Result>,
+    M: TypedMessage + 'static,
+{
+    fn send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> BoxFuture<'_, Result<ResponseHandle<M::Result>, ChannelSendError<()>>> {
+        let (envelope, rx) = envelope::pack::<A, M>(msg);
+        let envelope = envelope.with_meta(meta);
+        async move {
+            if !self.intake.try_enter() {
+                return Err(ChannelSendError::RateLimited(()));
+            }
+            self.sender
+                .send(envelope)
+                .await
+                .map_err(|_| ChannelSendError::Disconnected(()))?;
+            Ok(ResponseHandle(rx.into()))
+        }
+        .boxed()
+    }
+
+    fn try_send(
+        &self,
+        msg: M,
+        meta: Option<Box<EnvelopeMeta>>,
+    ) -> Result<ResponseHandle<M::Result>, ChannelTrySendError<()>> {
+        if !self.intake.try_enter() {
+            return Err(ChannelTrySendError::RateLimited(()));
+        }
+        let (envelope, rx) = envelope::pack::<A, M>(msg);
+        self.sender
+            .try_send(envelope.with_meta(meta))
+            .map_err(|err| ChannelTrySendError::from(err).map(drop))?;
+        Ok(ResponseHandle(rx.into()))
+    }
+
+    #[inline]
+    fn recipient(&self) -> Recipient<M> {
+        self.clone().into()
+    }
+}
@@ -0,0 +1,411 @@
+//! 响应通道
+//!
+//! 一次性的单值通道, 和`tokio::sync::oneshot`类似. 接收端丢弃时如果发送端也已经丢弃,
+//! 共享的状态回到当前线程的池中, 下一次[`channel`]直接复用, 所以稳定之后发送消息不再为响应分配内存.
+//!
+//! 只由接收端回收: 通道一般在接收的线程上创建, 发送端所在的actor线程放回的状态不会再被取出.
+
+use std::any::{Any, TypeId};
+use std::cell::{RefCell, UnsafeCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+/// 接收端设置了`rx_task`
+const RX_TASK_SET: usize = 1;
+/// 发送端已经完成, 可能没有写入响应
+const COMPLETE: usize = 2;
+/// 接收端已经丢弃
+const CLOSED: usize = 4;
+/// 等待取消的一端设置了`tx_task`
+const TX_TASK_SET: usize = 8;
+
+/// 每个线程每种响应类型最多缓存的数量
+const POOL_CAPACITY: usize = 1024;
+
+thread_local! {
+    /// 每种响应类型的`Vec<Arc<Shared<T>>>`
+    ///
+    /// 一个线程中的响应类型通常不多, 线性查找比哈希更快.
+    static POOL: RefCell<Vec<(TypeId, Box<dyn Any>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 和`tokio::sync::oneshot`一样, 只有对应的标志位没有设置的一方才能修改`rx_task`和`tx_task`
+struct Shared<T> {
+    state: AtomicUsize,
+    /// 发送端只在设置[`COMPLETE`]之前写入, 接收端只在看到[`COMPLETE`]之后读取
+    value: UnsafeCell<Option<T>>,
+    rx_task: UnsafeCell<Option<Waker>>,
+    tx_task: UnsafeCell<Option<Waker>>,
+}
+
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    #[inline]
+    fn new() -> Self {
+        Shared {
+            state: AtomicUsize::new(0),
+            value: UnsafeCell::new(None),
+            rx_task: UnsafeCell::new(None),
+            tx_task: UnsafeCell::new(None),
+        }
+    }
+
+    #[inline]
+    fn is_closed(&self) -> bool {
+        self.state.load(Ordering::Acquire) & CLOSED != 0
+    }
+
+    /// 只能有一方调用
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let state = self.state.load(Ordering::Acquire);
+        if state & CLOSED != 0 {
+            return Poll::Ready(());
+        }
+        if state & TX_TASK_SET != 0 {
+            if unsafe { will_wake(&self.tx_task, cx) } {
+                return Poll::Pending;
+            }
+            if self.state.fetch_and(!TX_TASK_SET, Ordering::AcqRel) & CLOSED != 0 {
+                // 接收端可能正在唤醒`tx_task`
+                return Poll::Ready(());
+            }
+        }
+        unsafe { *self.tx_task.get() = Some(cx.waker().clone()) };
+        if self.state.fetch_or(TX_TASK_SET, Ordering::AcqRel) & CLOSED != 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// 发送端完成, 需要时唤醒接收端
+    #[inline]
+    fn complete(&self) -> usize {
+        let prev = self.state.fetch_or(COMPLETE, Ordering::AcqRel);
+        if prev & (RX_TASK_SET | CLOSED) == RX_TASK_SET {
+            unsafe { wake(&self.rx_task) };
+        }
+        prev
+    }
+}
+
+#[inline]
+unsafe fn will_wake(task: &UnsafeCell<Option<Waker>>, cx: &Context<'_>) -> bool {
+    (*task.get())
+        .as_ref()
+        .is_some_and(|waker| waker.will_wake(cx.waker()))
+}
+
+#[inline]
+unsafe fn wake(task: &UnsafeCell<Option<Waker>>) {
+    if let Some(waker) = &*task.get() {
+        waker.wake_by_ref();
+    }
+}
+
+pub(crate) fn channel<T>() -> (RespTx<T>, RespRx<T>)
+where
+    T: Send + 'static,
+{
+    let shared = take::<T>().unwrap_or_else(|| Arc::new(Shared::new()));
+    (
+        RespTx {
+            shared: Some(Arc::clone(&shared)),
+        },
+        RespRx {
+            shared: Some(shared),
+        },
+    )
+}
+
+/// 从池中取出
+fn take<T>() -> Option<Arc<Shared<T>>>
+where
+    T: Send + 'static,
+{
+    POOL.try_with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let (_, list) = pool.iter_mut().find(|(id, _)| *id == TypeId::of::<T>())?;
+        list.downcast_mut::<Vec<Arc<Shared<T>>>>()?.pop()
+    })
+    .ok()
+    .flatten()
+}
+
+/// 发送端已经丢弃时, 重置之后放回池中
+fn recycle<T>(mut shared: Arc<Shared<T>>)
+where
+    T: Send + 'static,
+{
+    // 发送端还持有时直接丢弃, 先检查计数避免在共享的缓存行上做CAS
+    if Arc::strong_count(&shared) != 1 {
+        return;
+    }
+    let Some(inner) = Arc::get_mut(&mut shared) else {
+        return;
+    };
+    // 先丢弃旧的响应, 它的析构可能再次访问池
+    *inner = Shared::new();
+    POOL.try_with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let index = match pool.iter().position(|(id, _)| *id == TypeId::of::<T>()) {
+            Some(index) => index,
+            None => {
+                let list = Vec::<Arc<Shared<T>>>::with_capacity(POOL_CAPACITY);
+                pool.push((TypeId::of::<T>(), Box::new(list)));
+                pool.len() - 1
+            }
+        };
+        let list = pool[index]
+            .1
+            .downcast_mut::<Vec<Arc<Shared<T>>>>()
+            .expect("response pool type mismatch");
+        if list.len() < POOL_CAPACITY {
+            list.push(shared);
+        }
+    })
+    .ok();
+}
+
+/// 响应通道的发送端
+pub struct RespTx<T>
+where
+    T: Send + 'static,
+{
+    /// 只在完成之后为`None`
+    shared: Option<Arc<Shared<T>>>,
+}
+
+impl<T> RespTx<T>
+where
+    T: Send + 'static,
+{
+    #[inline]
+    fn shared(&self) -> &Arc<Shared<T>> {
+        self.shared.as_ref().expect("response channel is completed")
+    }
+
+    /// 接收端已经丢弃时返回原响应
+    pub fn send(mut self, value: T) -> Result<(), T> {
+        let shared = self.shared.take().expect("response channel is completed");
+        unsafe { *shared.value.get() = Some(value) };
+        if shared.complete() & CLOSED != 0 {
+            // 接收端已经丢弃, 不会再读取
+            Err(unsafe { (*shared.value.get()).take() }.expect("response is taken"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 接收端是否已经丢弃
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.shared().is_closed()
+    }
+
+    /// 只能检查是否被取消的句柄, 不影响响应
+    #[inline]
+    pub(crate) fn cancellation(&self) -> Cancellation {
+        Cancellation(Arc::clone(self.shared()) as _)
+    }
+}
+
+impl<T> Drop for RespTx<T>
+where
+    T: Send + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            shared.complete();
+        }
+    }
+}
+
+/// 发送端丢弃且没有写入响应
+#[derive(Debug)]
+pub struct RecvError;
+
+/// 响应通道的接收端
+pub struct RespRx<T>
+where
+    T: Send + 'static,
+{
+    /// 只在丢弃时为`None`
+    shared: Option<Arc<Shared<T>>>,
+}
+
+impl<T> RespRx<T>
+where
+    T: Send + 'static,
+{
+    #[inline]
+    fn shared(&self) -> &Shared<T> {
+        self.shared.as_ref().expect("response channel is dropped")
+    }
+
+    /// 发送端已经完成之后调用
+    #[inline]
+    fn consume(&self) -> Result<T, RecvError> {
+        unsafe { (*self.shared().value.get()).take() }.ok_or(RecvError)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, RecvError> {
+        if self.shared().state.load(Ordering::Acquire) & COMPLETE != 0 {
+            self.consume()
+        } else {
+            Err(RecvError)
+        }
+    }
+}
+
+impl<T> Future for RespRx<T>
+where
+    T: Send + 'static,
+{
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let shared = self.shared();
+        let state = shared.state.load(Ordering::Acquire);
+        if state & COMPLETE != 0 {
+            return Poll::Ready(self.consume());
+        }
+        if state & RX_TASK_SET != 0 {
+            if unsafe { will_wake(&shared.rx_task, cx) } {
+                return Poll::Pending;
+            }
+            if shared.state.fetch_and(!RX_TASK_SET, Ordering::AcqRel) & COMPLETE != 0 {
+                // 发送端可能正在唤醒`rx_task`
+                return Poll::Ready(self.consume());
+            }
+        }
+        unsafe { *shared.rx_task.get() = Some(cx.waker().clone()) };
+        if shared.state.fetch_or(RX_TASK_SET, Ordering::AcqRel) & COMPLETE != 0 {
+            Poll::Ready(self.consume())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for RespRx<T>
+where
+    T: Send + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            // 发送端已经完成时没有人关心接收端是否丢弃
+            if shared.state.load(Ordering::Acquire) & COMPLETE == 0 {
+                let prev = shared.state.fetch_or(CLOSED, Ordering::AcqRel);
+                if prev & (TX_TASK_SET | COMPLETE) == TX_TASK_SET {
+                    unsafe { wake(&shared.tx_task) };
+                }
+            }
+            recycle(shared);
+        }
+    }
+}
+
+/// 类型擦除的响应通道, 用于检查调用者是否已经放弃
+///
+/// 和[`RespTx`]共享状态, 创建时不分配内存. 同一个通道只能创建一个, 等待时会修改`tx_task`.
+pub(crate) struct Cancellation(Arc<dyn Closed>);
+
+impl Cancellation {
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    #[inline]
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.0.poll_closed(cx)
+    }
+}
+
+trait Closed: Send + Sync {
+    fn is_closed(&self) -> bool;
+
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()>;
+}
+
+impl<T> Closed for Shared<T>
+where
+    T: Send,
+{
+    #[inline]
+    fn is_closed(&self) -> bool {
+        Shared::is_closed(self)
+    }
+
+    #[inline]
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        Shared::poll_closed(self, cx)
+    }
+}
+
+/// [`ResponseHandle`](crate::ResponseHandle)接收的一端
+pub(crate) enum Receiver<T>
+where
+    T: Send + 'static,
+{
+    Local(RespRx<T>),
+    /// 远程调用的响应
+    #[cfg(feature = "remote")]
+    Remote(tokio::sync::oneshot::Receiver<T>),
+}
+
+impl<T> Receiver<T>
+where
+    T: Send + 'static,
+{
+    pub fn try_recv(&mut self) -> Result<T, RecvError> {
+        match self {
+            Receiver::Local(rx) => rx.try_recv(),
+            #[cfg(feature = "remote")]
+            Receiver::Remote(rx) => rx.try_recv().map_err(|_| RecvError),
+        }
+    }
+}
+
+impl<T> From<RespRx<T>> for Receiver<T>
+where
+    T: Send + 'static,
+{
+    #[inline]
+    fn from(rx: RespRx<T>) -> Self {
+        Receiver::Local(rx)
+    }
+}
+
+#[cfg(feature = "remote")]
+impl<T> From<tokio::sync::oneshot::Receiver<T>> for Receiver<T>
+where
+    T: Send + 'static,
+{
+    #[inline]
+    fn from(rx: tokio::sync::oneshot::Receiver<T>) -> Self {
+        Receiver::Remote(rx)
+    }
+}
+
+impl<T> Future for Receiver<T>
+where
+    T: Send + 'static,
+{
+    type Output = Result<T, RecvError>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.get_mut() {
+            Receiver::Local(rx) => Pin::new(rx).poll(cx),
+            #[cfg(feature = "remote")]
+            Receiver::Remote(rx) => Pin::new(rx).poll(cx).map_err(|_| RecvError),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// [`CallError`](crate::CallError)的种类, 用于判断是否重试
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CallErrorKind {
+    /// 信箱已满
+    Full,
+    /// 信箱已关闭
+    Disconnected,
+    /// 处理消息时panic, 或者actor正在重启
+    HandlerPanic,
+    /// 超出了信箱入口[`LimitMode::Reject`](crate::limiter::LimitMode::Reject)的限速
+    RateLimited,
+}
+
+/// [`LocalAddress::call_with_retry`](crate::LocalAddress::call_with_retry)的重试策略
+///
+/// 第`n`次重试之前等待`initial * multiplier^(n-1)`, 不超过`max_delay`.
+///
+/// ```ignore
+/// let policy = RetryPolicy::new(5)
+///     .with_backoff(Duration::from_millis(10), 2.0)
+///     .with_max_delay(Duration::from_secs(1))
+///     .with_jitter(0.5);
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: f64,
+    retry_if: Arc<dyn Fn(CallErrorKind) -> bool + Send + Sync>,
+}
+
+impl RetryPolicy {
+    /// 最多调用`max_attempts`次(包括第一次)
+    ///
+    /// 默认从10ms开始每次翻倍, 最多等待1s, 没有抖动,
+    /// 重试除了[`CallErrorKind::Disconnected`]之外的错误.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial: Duration::from_millis(10),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(1),
+            jitter: 0.0,
+            retry_if: Arc::new(|kind| kind != CallErrorKind::Disconnected),
+        }
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, multiplier: f64) -> Self {
+        self.initial = initial;
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// 随机减少等待时间的比例, 范围是`0.0..=1.0`
+    ///
+    /// 避免多个调用者同时重试.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 设置哪些错误可以重试
+    pub fn with_retry_if<F>(mut self, f: F) -> Self
+    where
+        F: Fn(CallErrorKind) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Arc::new(f);
+        self
+    }
+
+    #[inline]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    #[inline]
+    pub fn is_retryable(&self, kind: CallErrorKind) -> bool {
+        (self.retry_if)(kind)
+    }
+
+    /// 第`retry`次重试之前的等待时间, 从1开始
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exp = self.multiplier.powi(retry.saturating_sub(1) as i32);
+        let delay = (self.initial.as_secs_f64() * exp).min(self.max_delay.as_secs_f64());
+        let delay = if self.jitter > 0.0 {
+            delay * (1.0 - self.jitter * random())
+        } else {
+            delay
+        };
+        Duration::from_secs_f64(delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    #[inline]
+    fn default() -> Self {
+        RetryPolicy::new(3)
+    }
+}
+
+/// `[0, 1)`之间的随机数, 不需要密码学安全
+fn random() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
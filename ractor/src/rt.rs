@@ -0,0 +1,354 @@
+//! 异步运行时的抽象
+//!
+//! actor的核心只通过[`Executor`]和[`Timer`]生成任务和等待, 由feature选择运行时:
+//!
+//! - `rt-tokio`(默认): [`Tokio`]
+//! - `rt-async-std`: [`AsyncStd`]
+//! - `rt-smol`: [`Smol`]
+//!
+//! 同时启用多个时按照上面的顺序选择. [`testkit`](crate::testkit), [`Arbiter`](crate::Arbiter),
+//! [`SyncBroker`](crate::SyncBroker), [`unsend`](crate::unsend), `remote`和
+//! [`FileJournal`](crate::persistence::FileJournal)依赖tokio, 只在启用`rt-tokio`时可用.
+//!
+//! [`block_on`]在当前线程上运行`future`, 便于编写和运行时无关的`main`.
+
+use std::any::Any;
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::{select, AbortHandle, Abortable, BoxFuture, Either};
+use futures::FutureExt;
+use tokio::sync::oneshot;
+
+/// 生成任务
+pub trait Executor {
+    /// 在后台执行`future`, 不等待结果
+    fn spawn(future: BoxFuture<'static, ()>);
+}
+
+/// 定时器
+pub trait Timer {
+    fn sleep(dur: Duration) -> BoxFuture<'static, ()>;
+}
+
+#[cfg(feature = "rt-tokio")]
+pub type DefaultRuntime = Tokio;
+#[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+pub type DefaultRuntime = AsyncStd;
+#[cfg(all(
+    feature = "rt-smol",
+    not(any(feature = "rt-tokio", feature = "rt-async-std"))
+))]
+pub type DefaultRuntime = Smol;
+
+#[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std", feature = "rt-smol")))]
+compile_error!("one of the features `rt-tokio`, `rt-async-std` or `rt-smol` must be enabled");
+
+/// 需要在tokio的runtime中使用
+#[cfg(feature = "rt-tokio")]
+pub struct Tokio;
+
+#[cfg(feature = "rt-tokio")]
+impl Executor for Tokio {
+    #[inline]
+    fn spawn(future: BoxFuture<'static, ()>) {
+        tokio::spawn(future);
+    }
+}
+
+#[cfg(feature = "rt-tokio")]
+impl Timer for Tokio {
+    #[inline]
+    fn sleep(dur: Duration) -> BoxFuture<'static, ()> {
+        tokio::time::sleep(dur).boxed()
+    }
+}
+
+#[cfg(feature = "rt-async-std")]
+pub struct AsyncStd;
+
+#[cfg(feature = "rt-async-std")]
+impl Executor for AsyncStd {
+    #[inline]
+    fn spawn(future: BoxFuture<'static, ()>) {
+        async_std::task::spawn(future);
+    }
+}
+
+#[cfg(feature = "rt-async-std")]
+impl Timer for AsyncStd {
+    #[inline]
+    fn sleep(dur: Duration) -> BoxFuture<'static, ()> {
+        async_std::task::sleep(dur).boxed()
+    }
+}
+
+/// 在smol的全局executor上执行
+#[cfg(feature = "rt-smol")]
+pub struct Smol;
+
+#[cfg(feature = "rt-smol")]
+impl Executor for Smol {
+    #[inline]
+    fn spawn(future: BoxFuture<'static, ()>) {
+        smol::spawn(future).detach();
+    }
+}
+
+#[cfg(feature = "rt-smol")]
+impl Timer for Smol {
+    #[inline]
+    fn sleep(dur: Duration) -> BoxFuture<'static, ()> {
+        async move {
+            smol::Timer::after(dur).await;
+        }
+        .boxed()
+    }
+}
+
+/// 在当前线程上创建[`DefaultRuntime`]并运行`future`直到完成
+///
+/// 不能在异步上下文中调用.
+#[cfg(feature = "rt-tokio")]
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    tokio::runtime::Runtime::new()
+        .expect("failed to build the tokio runtime")
+        .block_on(future)
+}
+
+/// 在当前线程上创建[`DefaultRuntime`]并运行`future`直到完成
+///
+/// 不能在异步上下文中调用.
+#[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    async_std::task::block_on(future)
+}
+
+/// 在当前线程上创建[`DefaultRuntime`]并运行`future`直到完成
+///
+/// 不能在异步上下文中调用.
+#[cfg(all(
+    feature = "rt-smol",
+    not(any(feature = "rt-tokio", feature = "rt-async-std"))
+))]
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    smol::block_on(future)
+}
+
+/// 在[`DefaultRuntime`]上生成任务
+#[inline]
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_with(future, <DefaultRuntime as Executor>::spawn)
+}
+
+/// 通过`spawner`生成任务, 例如在指定的[`Arbiter`](crate::Arbiter)上
+pub(crate) fn spawn_with<F, S>(future: F, spawner: S) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+    S: FnOnce(BoxFuture<'static, ()>),
+{
+    let (tx, rx) = oneshot::channel();
+    let (abort, registration) = AbortHandle::new_pair();
+    spawner(
+        Abortable::new(
+            async move {
+                // panic交给`JoinHandle`, 而不是让运行时处理
+                tx.send(AssertUnwindSafe(future).catch_unwind().await).ok();
+            },
+            registration,
+        )
+        .map(|_| ())
+        .boxed(),
+    );
+    JoinHandle { rx, abort }
+}
+
+/// 生成任务的位置
+#[derive(Clone, Default)]
+pub(crate) enum Spawner {
+    /// [`DefaultRuntime`]
+    #[default]
+    Default,
+    /// 指定的tokio runtime, 例如[`Arbiter`](crate::Arbiter)和[`SyncBroker`](crate::SyncBroker)的
+    #[cfg(feature = "rt-tokio")]
+    Handle(tokio::runtime::Handle),
+}
+
+impl Spawner {
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self {
+            Spawner::Default => spawn(future),
+            #[cfg(feature = "rt-tokio")]
+            Spawner::Handle(handle) => spawn_with(future, |future| {
+                handle.spawn(future);
+            }),
+        }
+    }
+}
+
+/// 一组actor的[`Spawner`], 轮流分配给新的actor
+#[derive(Default)]
+pub(crate) struct Placement {
+    spawners: Vec<Spawner>,
+    next: AtomicUsize,
+}
+
+impl Placement {
+    #[inline]
+    #[cfg(feature = "rt-tokio")]
+    pub fn new(spawners: Vec<Spawner>) -> Self {
+        Placement {
+            spawners,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn next(&self) -> Spawner {
+        if self.spawners.is_empty() {
+            return Spawner::Default;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        self.spawners[next % self.spawners.len()].clone()
+    }
+}
+
+#[inline]
+pub async fn sleep(dur: Duration) {
+    <DefaultRuntime as Timer>::sleep(dur).await
+}
+
+/// 超时时返回[`Elapsed`]
+pub async fn timeout<F>(dur: Duration, future: F) -> Result<F::Output, Elapsed>
+where
+    F: Future,
+{
+    let sleep = sleep(dur);
+    futures::pin_mut!(future, sleep);
+    match select(future, sleep).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+/// 让出当前任务, 和运行时无关
+pub async fn yield_now() {
+    let mut yielded = false;
+    futures::future::poll_fn(move |cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// 任务的句柄, 等待它会得到任务的结果
+///
+/// 丢弃句柄不会停止任务.
+pub struct JoinHandle<T> {
+    rx: oneshot::Receiver<std::thread::Result<T>>,
+    abort: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    /// 停止任务
+    #[inline]
+    pub fn abort(&self) {
+        self.abort.abort()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx).map(|result| match result {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(panic)) => Err(JoinError(Some(panic))),
+            Err(_) => Err(JoinError(None)),
+        })
+    }
+}
+
+/// 任务被停止或者panic
+pub struct JoinError(Option<Box<dyn Any + Send>>);
+
+impl JoinError {
+    #[inline]
+    pub fn is_panic(&self) -> bool {
+        self.0.is_some()
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// 取出panic的内容, 被停止时返回`None`
+    #[inline]
+    pub fn into_panic(self) -> Option<Box<dyn Any + Send>> {
+        self.0
+    }
+}
+
+impl Debug for JoinError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_panic() {
+            write!(f, "The task panicked.")
+        } else {
+            write!(f, "The task was aborted.")
+        }
+    }
+}
+
+impl Display for JoinError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+/// [`timeout`]超时
+pub struct Elapsed;
+
+impl Debug for Elapsed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The deadline has elapsed.")
+    }
+}
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Elapsed {}
@@ -0,0 +1,109 @@
+//! 与[tower](https://docs.rs/tower)互通
+//!
+//! [`LocalAddress`]实现了[`Service`], 可以使用tower的超时, 限流, 重试等中间件;
+//! [`ServiceActor`]则反过来把一个[`Service`]包装成actor.
+
+use std::task::{Context as TaskContext, Poll};
+
+use futures::future::{poll_fn, BoxFuture};
+use tower_service::Service;
+
+use crate::address::CallError;
+use crate::message::Message;
+use crate::{Actor, Context, LocalAddress, MessageHandler};
+
+impl<A> LocalAddress<A>
+where
+    A: Actor,
+{
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.sender.len() >= A::MAIL_BOX_SIZE as usize
+    }
+}
+
+/// `poll_ready`在信箱已满时返回`Pending`, 直到有actor取出消息
+///
+/// 信箱已关闭时`poll_ready`仍然返回`Ready`, 错误由`call`返回.
+impl<A, M> Service<M> for LocalAddress<A>
+where
+    M: Message + 'static,
+    A: MessageHandler<M>,
+{
+    type Response = <A as MessageHandler<M>>::Output;
+    type Error = CallError<A>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.is_full() {
+            return Poll::Ready(Ok(()));
+        }
+        self.intake.register_space(cx.waker());
+        // 注册之前可能已经有消息被取出
+        if self.is_full() {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn call(&mut self, msg: M) -> Self::Future {
+        let addr = self.clone();
+        Box::pin(async move { addr.call(msg).await })
+    }
+}
+
+/// 把请求交给内部[`Service`]处理的actor
+///
+/// 每个actor持有一份`S`的clone, 同一个actor内的请求是依次处理的,
+/// 需要并发时生成多个actor.
+///
+/// ```ignore
+/// let broker = Broker::<ServiceActor<MyService>>::spawn_with_args(4, false, my_service).await;
+/// let resp = broker.call(request).await?;
+/// ```
+pub struct ServiceActor<S> {
+    service: S,
+}
+
+impl<S> ServiceActor<S> {
+    #[inline]
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+}
+
+impl<S> Actor for ServiceActor<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    const MAIL_BOX_SIZE: u32 = 1024;
+    type Args = S;
+
+    async fn create(ctx: &mut Context<Self>) -> Self {
+        ServiceActor {
+            service: ctx.create_args.clone(),
+        }
+    }
+}
+
+impl<S, M> MessageHandler<M> for ServiceActor<S>
+where
+    M: Message + 'static,
+    S: Service<M> + Clone + Send + Sync + 'static,
+    S::Response: Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    async fn handle(&mut self, msg: M, _ctx: &mut Context<Self>) -> Self::Output {
+        poll_fn(|cx| self.service.poll_ready(cx)).await?;
+        self.service.call(msg).await
+    }
+}
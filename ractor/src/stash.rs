@@ -0,0 +1,180 @@
+use std::any::Any;
+use std::collections::VecDeque;
+
+use crate::actor::UnsendActor;
+use crate::envelope::{self, Envelope, RespTx};
+use crate::message::{Message, MessageHandler, UnsendHandler};
+use crate::meta::EnvelopeMeta;
+
+/// 单个actor的消息暂存区
+///
+/// 暂存的消息会在[`Context::unstash_all`]之后, 按照暂存的顺序先于信箱中的消息处理.
+pub(crate) struct Stash<A: ?Sized>
+where
+    A: UnsendActor,
+{
+    capacity: usize,
+    stashed: VecDeque<Envelope<A>>,
+    /// 已经取出, 等待优先处理的消息
+    unstashed: VecDeque<Envelope<A>>,
+    /// 本次处理期间暂存的消息
+    ///
+    /// 处理结束之后才会打包, 以便将当前消息的响应转交给[`Context::stash_current`]暂存的消息
+    pending: Vec<Pending<A>>,
+}
+
+struct Pending<A: ?Sized>
+where
+    A: UnsendActor,
+{
+    msg: Box<dyn Any + Send>,
+    message_type: &'static str,
+    pack: fn(Box<dyn Any + Send>) -> Envelope<A>,
+    meta: Option<Box<EnvelopeMeta>>,
+    /// 是否为正在处理的消息, 见[`Context::stash_current`]
+    current: bool,
+}
+
+impl<A> Stash<A>
+where
+    A: UnsendActor,
+{
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Stash {
+            capacity,
+            stashed: VecDeque::new(),
+            unstashed: VecDeque::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.stashed.len() + self.pending.len()
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+
+    pub fn push<M>(&mut self, msg: M, meta: Option<Box<EnvelopeMeta>>, current: bool)
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        self.pending.push(Pending {
+            msg: Box::new(msg),
+            message_type: std::any::type_name::<M>(),
+            pack: repack::<A, M>,
+            meta,
+            current,
+        })
+    }
+
+    /// 直接暂存一个已打包的消息
+    #[inline]
+    pub fn push_envelope(&mut self, envelope: Envelope<A>) {
+        self.stashed.push_back(envelope)
+    }
+
+    #[inline]
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// 如果本次处理中通过[`Context::stash_current`]暂存了正在处理的消息, 它接管`tx`, 由`pack_current`打包.
+    ///
+    /// 否则原样返回`tx`
+    pub fn adopt<M>(
+        &mut self,
+        tx: RespTx<<A as UnsendHandler<M>>::Output>,
+        pack_current: fn(M, RespTx<<A as UnsendHandler<M>>::Output>) -> Envelope<A>,
+    ) -> Option<RespTx<<A as UnsendHandler<M>>::Output>>
+    where
+        M: Message + 'static,
+        A: UnsendHandler<M>,
+    {
+        let mut tx = Some(tx);
+        for pending in self.pending.drain(..) {
+            let Pending { msg, message_type, pack, meta, current } = pending;
+            let envelope = match tx.take() {
+                Some(t) if current => match msg.downcast::<M>() {
+                    Ok(msg) => pack_current(*msg, t),
+                    Err(msg) => {
+                        log::warn!(
+                            "`{}` is stashed as the current message while handling `{}`, its response is not taken over.",
+                            message_type,
+                            std::any::type_name::<M>()
+                        );
+                        tx = Some(t);
+                        pack(msg)
+                    }
+                },
+                t => {
+                    tx = t;
+                    pack(msg)
+                }
+            };
+            self.stashed.push_back(envelope.with_meta(meta));
+        }
+        tx
+    }
+
+    /// 打包所有未被[`Stash::adopt`]的消息
+    pub fn flush(&mut self) {
+        for Pending { msg, pack, meta, .. } in self.pending.drain(..) {
+            self.stashed.push_back(pack(msg).with_meta(meta));
+        }
+    }
+
+    #[inline]
+    pub fn unstash_all(&mut self) {
+        self.unstashed.extend(self.stashed.drain(..));
+    }
+
+    #[inline]
+    pub fn next_unstashed(&mut self) -> Option<Envelope<A>> {
+        self.unstashed.pop_front()
+    }
+}
+
+impl<A: ?Sized> Stash<A>
+where
+    A: UnsendActor,
+{
+    /// 丢弃所有暂存的消息, 作为死信记录
+    pub fn clear(&mut self) {
+        let dropped = self
+            .unstashed
+            .drain(..)
+            .chain(self.stashed.drain(..))
+            .map(|envelope| envelope.message_type())
+            .chain(self.pending.drain(..).map(|pending| pending.message_type));
+        for message_type in dropped {
+            log::warn!(
+                "dead letter: stashed `{}` is dropped by `{}`.",
+                message_type,
+                std::any::type_name::<A>()
+            );
+        }
+    }
+}
+
+impl<A: ?Sized> Drop for Stash<A>
+where
+    A: UnsendActor,
+{
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn repack<A, M>(msg: Box<dyn Any + Send>) -> Envelope<A>
+where
+    M: Message + 'static,
+    A: MessageHandler<M>,
+{
+    envelope::pack_detached::<A, M>(*msg.downcast::<M>().expect("stashed message type mismatch"))
+}
@@ -0,0 +1,274 @@
+use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+
+use futures::future::join_all;
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+
+use crate::actor_runner::{ActorExit, ActorRunner};
+use crate::broker::{new_global_context, SpawnHandle};
+#[cfg(feature = "introspect")]
+use crate::introspect;
+use crate::message::Message;
+use crate::rt::{Placement, Spawner};
+use crate::{Actor, Context, LocalAddress, MessageHandler};
+
+/// 在专用线程上运行的actor
+///
+/// [`SyncBroker`]为每个actor生成一个线程, 线程空闲时阻塞在共享的信箱上.
+/// 消息由[`SyncHandler`]同步处理, 可以执行阻塞操作(压缩, SQLite等)而不会阻塞tokio的工作线程,
+/// 一个actor阻塞时, 信箱中的其他消息由其他线程处理.
+///
+/// 每个broker另外有一个tokio runtime, actor中可以使用tokio的网络和定时器,
+/// [`Context::defer`]的任务也在上面执行.
+///
+/// 仍然通过[`LocalAddress`]发送消息, 和普通的actor没有区别.
+pub trait SyncActor: Actor {
+    /// 线程的栈大小, 为`None`时使用标准库的默认值
+    const STACK_SIZE: Option<usize> = None;
+}
+
+/// 同步处理消息`M`
+///
+/// 所有`SyncHandler`都自动实现了[`MessageHandler`].
+/// 处理时会阻塞所在的线程, 只应该用于由[`SyncBroker`]生成的actor.
+///
+/// ```ignore
+/// impl SyncHandler<Compress> for Compressor {
+///     type Output = Vec<u8>;
+///
+///     fn handle(&mut self, msg: Compress, _ctx: &mut Context<Self>) -> Self::Output {
+///         zstd::encode_all(&msg.0[..], 3).unwrap()
+///     }
+/// }
+/// ```
+pub trait SyncHandler<M>: SyncActor
+where
+    M: Message,
+{
+    type Output: Send + 'static;
+
+    fn handle(&mut self, msg: M, ctx: &mut Context<Self>) -> Self::Output;
+}
+
+impl<A, M> MessageHandler<M> for A
+where
+    A: SyncHandler<M>,
+    M: Message,
+{
+    type Output = <A as SyncHandler<M>>::Output;
+
+    #[inline]
+    async fn handle(&mut self, msg: M, ctx: &mut Context<Self>) -> Self::Output {
+        <A as SyncHandler<M>>::handle(self, msg, ctx)
+    }
+}
+
+/// 在专用线程上生成[`SyncActor`]
+pub struct SyncBroker<A>
+where
+    A: SyncActor,
+{
+    addr: Arc<LocalAddress<A>>,
+    handles: Vec<SpawnHandle<A>>,
+    runtime: Io,
+    /// 同[`Broker`](crate::Broker), actor全部结束之后依然出现在快照中
+    #[cfg(feature = "introspect")]
+    _registration: introspect::Registration,
+}
+
+impl<A> SyncBroker<A>
+where
+    A: SyncActor,
+{
+    #[inline]
+    pub fn spawn(quantity: usize) -> Self
+    where
+        A::Args: Default,
+    {
+        SyncBroker::spawn_with_args(quantity, Default::default())
+    }
+
+    /// 生成`quantity`个线程, 每个线程运行一个actor
+    ///
+    /// [`Actor::create`]在各自的线程上执行.
+    pub fn spawn_with_args(quantity: usize, args: A::Args) -> Self {
+        let runtime = Io::new::<A>();
+        let handle = runtime.handle().clone();
+        let placement = Placement::new(vec![Spawner::Handle(handle.clone())]);
+        let (addr, global_context) = new_global_context(args, placement);
+
+        let handles = (0..quantity)
+            .map(|_| {
+                let mut context = Context::new(global_context.clone());
+                let status = Arc::clone(&context.status);
+                let (tx, rx) = oneshot::channel();
+                let handle = handle.clone();
+                let mut builder = std::thread::Builder::new().name(format!(
+                    "{}-{}",
+                    std::any::type_name::<A>(),
+                    status.id()
+                ));
+                if let Some(size) = A::STACK_SIZE {
+                    builder = builder.stack_size(size);
+                }
+                builder
+                    .spawn(move || {
+                        // 定时器和网络由broker的runtime驱动
+                        let _guard = handle.enter();
+                        let exit = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                            futures::executor::block_on(async move {
+                                let actor = A::create(&mut context).await;
+                                ActorRunner::blocking(actor, context).run().await
+                            })
+                        }));
+                        tx.send(exit).ok();
+                    })
+                    .expect("failed to spawn the thread of the sync actor");
+                SpawnHandle::from_thread(rx, status)
+            })
+            .collect();
+
+        SyncBroker {
+            addr,
+            handles,
+            runtime,
+            #[cfg(feature = "introspect")]
+            _registration: Arc::clone(&global_context.registration),
+        }
+    }
+
+    #[inline]
+    pub fn addr(&self) -> &LocalAddress<A> {
+        &self.addr
+    }
+
+    /// 等待所有actor结束, 按照生成的顺序返回每个actor的[`ActorExit`]
+    ///
+    /// 如果被[`LocalAddress::pause`]暂停, 会先恢复.
+    pub async fn wait_for_actors(self) -> Vec<ActorExit> {
+        self.addr.resume();
+        drop(self.addr);
+        let exits = join_all(self.handles.into_iter().map(SpawnHandle::join)).await;
+        drop(self.runtime);
+        exits
+    }
+
+    /// 阻塞当前线程, 等待所有actor结束
+    ///
+    /// 不能在异步上下文中调用.
+    pub fn join(self) -> Vec<ActorExit> {
+        self.addr.resume();
+        drop(self.addr);
+        let exits =
+            futures::executor::block_on(join_all(self.handles.into_iter().map(SpawnHandle::join)));
+        drop(self.runtime);
+        exits
+    }
+}
+
+/// [`SyncBroker`]的runtime, 驱动定时器和网络, 执行[`Context::defer`]的任务
+///
+/// 丢弃时不等待尚未结束的任务, 可以在异步上下文中丢弃.
+struct Io(Option<Runtime>);
+
+impl Io {
+    fn new<A>() -> Self
+    where
+        A: SyncActor,
+    {
+        Io(Some(
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name(format!("{}-io", std::any::type_name::<A>()))
+                .enable_all()
+                .build()
+                .expect("failed to build the runtime of the sync actor"),
+        ))
+    }
+
+    #[inline]
+    fn handle(&self) -> &tokio::runtime::Handle {
+        self.0.as_ref().unwrap().handle()
+    }
+}
+
+impl Drop for Io {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl<A> Deref for SyncBroker<A>
+where
+    A: SyncActor,
+{
+    type Target = LocalAddress<A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SyncActor, SyncBroker, SyncHandler};
+    use crate::{Actor, Context};
+
+    struct Worker;
+
+    /// 给自己发送一条`Sleep`之后阻塞
+    struct SelfSend(Duration);
+    struct Sleep(Duration);
+
+    impl Actor for Worker {
+        const MAIL_BOX_SIZE: u32 = 8;
+        type Args = ();
+
+        async fn create(_ctx: &mut Context<Self>) -> Self {
+            Worker
+        }
+    }
+
+    impl SyncActor for Worker {}
+
+    impl SyncHandler<SelfSend> for Worker {
+        type Output = ();
+
+        fn handle(&mut self, SelfSend(dur): SelfSend, ctx: &mut Context<Self>) {
+            let addr = ctx.self_addr.upgrade().unwrap();
+            addr.try_do_send(Sleep(Duration::ZERO)).ok();
+            std::thread::sleep(dur);
+        }
+    }
+
+    impl SyncHandler<Sleep> for Worker {
+        type Output = ();
+
+        fn handle(&mut self, Sleep(dur): Sleep, _ctx: &mut Context<Self>) {
+            std::thread::sleep(dur);
+        }
+    }
+
+    #[tokio::test]
+    async fn blocked_actor_does_not_stall_siblings() {
+        let broker = SyncBroker::<Worker>::spawn(2);
+        broker
+            .do_send(SelfSend(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let start = Instant::now();
+        broker.call(Sleep(Duration::ZERO)).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        let exits = broker.wait_for_actors().await;
+        assert!(exits.iter().all(|exit| exit.is_clean()));
+    }
+}
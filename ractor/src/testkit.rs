@@ -0,0 +1,481 @@
+//! 用于测试actor的工具
+//!
+//! 配合tokio的暂停时间(`#[tokio::test(start_paused = true)]`)使用时,
+//! 定时器和[`State::Sleep`](crate::State::Sleep)会在runtime空闲时自动推进, 测试结果是确定的.
+//!
+//! ```ignore
+//! #[tokio::test(start_paused = true)]
+//! async fn counter() {
+//!     let mut actor = TestActor::<Counter>::new(()).await;
+//!     let resp = actor.send(Incr).await;
+//!     assert!(actor.step().await);
+//!     assert_eq!(resp.recv().await.unwrap(), 1);
+//! }
+//! ```
+
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::FutureExt;
+use tokio::sync::mpsc;
+
+use crate::actor_runner::{ActorRunner, StoppingPosition};
+use crate::broker::new_global_context;
+use crate::envelope::Envelope;
+use crate::message::Message;
+use crate::{Actor, Broker, Context, LocalAddress, MessageHandler, ResponseHandle};
+
+/// [`TestProbe`]的默认等待时间
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 推进tokio的暂停时间, 见[`tokio::time::advance`]
+#[inline]
+pub async fn advance(dur: Duration) {
+    tokio::time::advance(dur).await
+}
+
+/// 记录收到的消息的actor
+pub struct Probe<M> {
+    tx: mpsc::UnboundedSender<M>,
+}
+
+impl<M> Actor for Probe<M>
+where
+    M: Message + 'static,
+{
+    const MAIL_BOX_SIZE: u32 = 1024;
+    type Args = mpsc::UnboundedSender<M>;
+
+    async fn create(ctx: &mut Context<Self>) -> Self
+    where
+        Self: Sized,
+    {
+        Probe {
+            tx: ctx.create_args.clone(),
+        }
+    }
+}
+
+impl<M> MessageHandler<M> for Probe<M>
+where
+    M: Message + 'static,
+{
+    type Output = ();
+
+    async fn handle(&mut self, msg: M, _ctx: &mut Context<Self>) -> Self::Output {
+        self.tx.send(msg).ok();
+    }
+}
+
+/// 记录收到的消息并断言
+///
+/// 把[`TestProbe::addr`]交给被测试的actor, 然后断言它发送了哪些消息.
+/// 断言失败时panic.
+pub struct TestProbe<M>
+where
+    M: Message + 'static,
+{
+    broker: Broker<Probe<M>>,
+    rx: mpsc::UnboundedReceiver<M>,
+    timeout: Duration,
+}
+
+impl<M> TestProbe<M>
+where
+    M: Message + 'static,
+{
+    pub async fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        TestProbe {
+            broker: Broker::spawn_with_args(1, false, tx).await,
+            rx,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// 设置`expect_*`的等待时间
+    #[inline]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[inline]
+    pub fn addr(&self) -> &LocalAddress<Probe<M>> {
+        self.broker.addr()
+    }
+
+    /// 等待下一条消息
+    pub async fn expect_msg(&mut self) -> M {
+        match tokio::time::timeout(self.timeout, self.rx.recv()).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => panic!("the probe has been stopped"),
+            Err(_) => panic!("timeout ({:?}) while waiting for a message", self.timeout),
+        }
+    }
+
+    /// 等待下一条消息并断言它满足`f`
+    pub async fn expect_msg_that<F>(&mut self, f: F) -> M
+    where
+        F: FnOnce(&M) -> bool,
+    {
+        let msg = self.expect_msg().await;
+        assert!(
+            f(&msg),
+            "the received message `{}` does not match",
+            std::any::type_name::<M>()
+        );
+        msg
+    }
+
+    /// 断言在`dur`内没有收到消息
+    pub async fn expect_no_msg(&mut self, dur: Duration) {
+        if let Ok(Some(_)) = tokio::time::timeout(dur, self.rx.recv()).await {
+            panic!("received an unexpected message within {:?}", dur);
+        }
+    }
+
+    /// 取出已经收到的消息, 不等待
+    #[inline]
+    pub fn try_recv(&mut self) -> Option<M> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// 在当前任务中逐条处理消息的actor
+///
+/// 不会产生新的任务, 只有调用[`TestActor::step`]时才会处理消息,
+/// 可以在每一步之间检查actor的状态.
+///
+/// 和[`Broker`]中的actor共用同一套生命周期: [`State::Reset`](crate::State::Reset)之后重新调用[`Actor::started`],
+/// panic交给[`Actor::catch_unwind`], 按照[`Actor::MAX_RESTARTS`]重启或者结束.
+/// 从信箱中取出消息时同样受限流和暂停的影响.
+pub struct TestActor<A>
+where
+    A: Actor,
+{
+    runner: ActorRunner<A>,
+    addr: Arc<LocalAddress<A>>,
+    stopped: bool,
+}
+
+/// [`TestActor`]的下一步操作
+enum Action<A>
+where
+    A: Actor,
+{
+    Begin,
+    Handle(Envelope<A>),
+    Finish(StoppingPosition),
+}
+
+impl<A> TestActor<A>
+where
+    A: Actor,
+{
+    /// 创建actor并调用[`Actor::started`]
+    ///
+    /// 在[`Actor::create`]中停止时不会调用`started`.
+    pub async fn new(args: A::Args) -> Self {
+        let (addr, global_context) = new_global_context(args, Default::default());
+        let mut context = Context::new(global_context);
+        let actor = A::create(&mut context).await;
+        let mut test_actor = TestActor {
+            runner: ActorRunner::new(actor, context),
+            addr,
+            stopped: false,
+        };
+        test_actor.drive(Action::Begin).await;
+        test_actor
+    }
+
+    #[inline]
+    pub fn addr(&self) -> &LocalAddress<A> {
+        &self.addr
+    }
+
+    #[inline]
+    pub fn actor(&self) -> &A {
+        &self.runner.actor
+    }
+
+    #[inline]
+    pub fn actor_mut(&mut self) -> &mut A {
+        &mut self.runner.actor
+    }
+
+    #[inline]
+    pub fn context(&self) -> &Context<A> {
+        &self.runner.context
+    }
+
+    /// actor是否已经停止
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// 最后一次panic的信息, 见[`ActorExit::panic`](crate::ActorExit::panic)
+    #[inline]
+    pub fn panic(&self) -> Option<&str> {
+        self.runner.panic.as_deref()
+    }
+
+    /// 发送消息, 在[`TestActor::step`]之前不会被处理
+    pub async fn send<M>(&self, msg: M) -> ResponseHandle<<A as MessageHandler<M>>::Output>
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        self.addr.send(msg).await.expect("the mailbox is closed")
+    }
+
+    /// 发送消息并立即处理, 返回处理结果
+    pub async fn call<M>(&mut self, msg: M) -> <A as MessageHandler<M>>::Output
+    where
+        M: Message + 'static,
+        A: MessageHandler<M>,
+    {
+        let mut resp = self.send(msg).await;
+        while self.step().await {
+            if let Ok(output) = resp.try_recv() {
+                return output;
+            }
+        }
+        resp.try_recv().expect("no response")
+    }
+
+    /// 处理一条已经就绪的消息
+    ///
+    /// 依次从暂存区, 已完成的[`Context::defer`]任务和信箱中取出, 不等待新的消息,
+    /// 但会等待限流. 没有可以处理的消息或actor已经停止时返回`false`.
+    pub async fn step(&mut self) -> bool {
+        if self.stopped {
+            return false;
+        }
+        let envelope = match self.runner.try_next_envelope().await {
+            Some(envelope) => envelope,
+            None => return false,
+        };
+        self.drive(Action::Handle(envelope)).await;
+        true
+    }
+
+    /// 处理所有已经就绪的消息, 返回处理的数量
+    pub async fn run_until_idle(&mut self) -> usize {
+        let mut count = 0;
+        while self.step().await {
+            count += 1;
+        }
+        count
+    }
+
+    /// 停止actor并调用[`Actor::stopped`]
+    ///
+    /// 和[`Broker`]中的actor一样, 在`stopped`中[`Context::reset`]时会重新开始.
+    pub async fn stop(&mut self) {
+        if !self.stopped {
+            self.drive(Action::Finish(StoppingPosition::Message)).await
+        }
+    }
+
+    /// 执行`action`直到可以处理下一条消息或者结束, 同[`ActorRunner::run`]一样捕获panic
+    async fn drive(&mut self, action: Action<A>) {
+        let mut action = Some(action);
+        loop {
+            let runner = &mut self.runner;
+            let result = AssertUnwindSafe(async {
+                let next = match action.take() {
+                    Some(Action::Handle(envelope)) => runner.step(envelope).await,
+                    Some(Action::Finish(pos)) => runner.finish(pos).await,
+                    // panic之后重启时重新开始生命周期
+                    Some(Action::Begin) | None => runner.begin().await,
+                };
+                runner.advance(next).await
+            })
+            .catch_unwind()
+            .await;
+            match result {
+                Ok(running) => {
+                    self.stopped = !running;
+                    return;
+                }
+                // 同`State::Abort`, 不调用`stopped`
+                Err(err) => {
+                    if !self.runner.recover(err).await {
+                        self.stopped = true;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::TestActor;
+    use crate::limiter::RateLimiter;
+    use crate::{Actor, Context, MessageHandler, State, StoppingPosition};
+
+    #[derive(Default)]
+    struct Counter {
+        total: u32,
+        starts: u32,
+        /// 为`true`时在`stopped`中重置
+        reset_on_stop: bool,
+    }
+
+    struct Add(u32);
+    struct Reset;
+    struct Panic;
+    struct Unstash;
+
+    impl Actor for Counter {
+        const MAIL_BOX_SIZE: u32 = 8;
+        const MAX_RESTARTS: u16 = 1;
+        /// 为`true`时在`create`中停止
+        type Args = bool;
+
+        async fn create(ctx: &mut Context<Self>) -> Self {
+            if ctx.create_args {
+                ctx.stop();
+            }
+            Counter::default()
+        }
+
+        async fn started(&mut self, ctx: &mut Context<Self>) {
+            self.starts += 1;
+            // 重启之后补上一条消息
+            if self.starts > 1 {
+                ctx.stash(Add(10)).ok();
+            }
+        }
+
+        async fn stopped(&mut self, ctx: &mut Context<Self>, _pos: StoppingPosition) {
+            if std::mem::take(&mut self.reset_on_stop) {
+                ctx.reset();
+            }
+        }
+
+        async fn reset(&mut self, _ctx: &mut Context<Self>) {
+            self.total = 0;
+        }
+
+        fn catch_unwind(&mut self, _err: &(dyn Any + Send), ctx: &mut Context<Self>) {
+            ctx.state = State::Reset;
+        }
+    }
+
+    impl MessageHandler<Add> for Counter {
+        type Output = u32;
+
+        async fn handle(&mut self, Add(n): Add, _ctx: &mut Context<Self>) -> u32 {
+            self.total += n;
+            self.total
+        }
+    }
+
+    impl MessageHandler<Reset> for Counter {
+        type Output = ();
+
+        async fn handle(&mut self, _: Reset, ctx: &mut Context<Self>) {
+            ctx.reset();
+        }
+    }
+
+    impl MessageHandler<Panic> for Counter {
+        type Output = ();
+
+        async fn handle(&mut self, _: Panic, _ctx: &mut Context<Self>) {
+            panic!("expected");
+        }
+    }
+
+    impl MessageHandler<Unstash> for Counter {
+        type Output = ();
+
+        async fn handle(&mut self, _: Unstash, ctx: &mut Context<Self>) {
+            ctx.unstash_all();
+        }
+    }
+
+    #[tokio::test]
+    async fn reset_restarts_lifecycle() {
+        let mut actor = TestActor::<Counter>::new(false).await;
+        assert_eq!(actor.actor().starts, 1);
+        assert_eq!(actor.call(Add(2)).await, 2);
+        actor.call(Reset).await;
+        assert_eq!(actor.actor().starts, 2);
+        assert_eq!(actor.actor().total, 0);
+        assert!(!actor.is_stopped());
+    }
+
+    #[tokio::test]
+    async fn reset_flushes_stash_of_started() {
+        let mut actor = TestActor::<Counter>::new(false).await;
+        actor.call(Reset).await;
+        assert_eq!(actor.context().stashed_count(), 1);
+        actor.call(Unstash).await;
+        assert_eq!(actor.run_until_idle().await, 1);
+        assert_eq!(actor.actor().total, 10);
+    }
+
+    #[tokio::test]
+    async fn panic_restarts_until_exhausted() {
+        let mut actor = TestActor::<Counter>::new(false).await;
+        actor.call(Add(1)).await;
+        let resp = actor.send(Panic).await;
+        assert!(actor.step().await);
+        assert!(resp.recv().await.is_err());
+        assert_eq!(actor.panic(), Some("expected"));
+        assert_eq!(actor.actor().starts, 2);
+        assert_eq!(actor.actor().total, 0);
+        assert!(!actor.is_stopped());
+
+        actor.send(Panic).await;
+        assert!(actor.step().await);
+        assert!(actor.is_stopped());
+        assert!(!actor.step().await);
+    }
+
+    #[tokio::test]
+    async fn stop_in_create_skips_started() {
+        let actor = TestActor::<Counter>::new(true).await;
+        assert!(actor.is_stopped());
+        assert_eq!(actor.actor().starts, 0);
+    }
+
+    #[tokio::test]
+    async fn reset_in_stopped_restarts() {
+        let mut actor = TestActor::<Counter>::new(false).await;
+        actor.actor_mut().reset_on_stop = true;
+        actor.stop().await;
+        assert!(!actor.is_stopped());
+        assert_eq!(actor.actor().starts, 2);
+
+        actor.stop().await;
+        assert!(actor.is_stopped());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn step_applies_rate_limit() {
+        let mut actor = TestActor::<Counter>::new(false).await;
+        actor
+            .addr()
+            .set_rate_limit(Some(RateLimiter::per_second(1)));
+        for _ in 0..3 {
+            actor.send(Add(1)).await;
+        }
+        let start = Instant::now();
+        assert_eq!(actor.run_until_idle().await, 3);
+        assert!(start.elapsed() >= Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,324 @@
+//! 不需要`Send`的actor
+//!
+//! [`UnsendActor`]可以持有`Rc`, `RefCell`或者不能跨线程的FFI句柄,
+//! 由[`UnsendBroker`]通过[`tokio::task::spawn_local`]生成在当前线程的`LocalSet`上.
+//! 只要消息和响应是`Send`的, 其他线程仍然可以通过[`UnsendAddress`]发送消息.
+//!
+//! [`UnsendActor`]和[`UnsendHandler`]是[`Actor`](crate::Actor)和[`MessageHandler`](crate::MessageHandler)
+//! 去掉`Send`要求的版本, 使用同一个[`Context`]和同一套运行流程, 因此[`State`](crate::State),
+//! 重启, 暂停和限流, [`ActorExit`]以及[`introspect`](crate::introspect)都和普通的actor相同.
+//! 暂存区, 行为切换和[`Context::defer`]要求actor实现[`MessageHandler`](crate::MessageHandler),
+//! 直接实现[`UnsendActor`]的actor不能使用.
+//!
+//! ```ignore
+//! struct Cache(Rc<RefCell<HashMap<String, String>>>);
+//!
+//! impl UnsendActor for Cache {
+//!     const MAIL_BOX_SIZE: u32 = 64;
+//!     type Args = ();
+//!
+//!     async fn create(_ctx: &mut Context<Self>) -> Self {
+//!         Cache(Default::default())
+//!     }
+//! }
+//!
+//! impl UnsendHandler<Get> for Cache {
+//!     type Output = Option<String>;
+//!
+//!     async fn handle(&mut self, msg: Get, _ctx: &mut Context<Self>) -> Self::Output {
+//!         self.0.borrow().get(msg.0).cloned()
+//!     }
+//! }
+//!
+//! let local = tokio::task::LocalSet::new();
+//! local
+//!     .run_until(async {
+//!         let broker = UnsendBroker::<Cache>::spawn(1);
+//!         let addr = broker.addr().clone();
+//!         // 可以发送到其他线程
+//!         tokio::spawn(async move { addr.call(Get("key")).await });
+//!     })
+//!     .await;
+//! ```
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use futures::future::join_all;
+
+pub use crate::actor::UnsendActor;
+use crate::actor_runner::{ActorExit, ActorRunner};
+use crate::broker::{new_global_context, SpawnHandle};
+use crate::envelope::{self, Envelope};
+use crate::error::{ChannelSendError, ChannelTrySendError};
+use crate::message::Message;
+pub use crate::message::UnsendHandler;
+use crate::rt::Placement;
+use crate::{CallError, Context, LocalAddress, ResponseHandle};
+
+/// [`UnsendActor`]的地址, 可以发送到其他线程
+///
+/// 在[`LocalAddress`]的基础上可以发送[`UnsendHandler`]处理的消息,
+/// 暂停, 限流和拦截器等通过`Deref`使用[`LocalAddress`]的方法.
+pub struct UnsendAddress<A>(LocalAddress<A>)
+where
+    A: UnsendActor;
+
+impl<A> UnsendAddress<A>
+where
+    A: UnsendActor,
+{
+    pub async fn send<M>(
+        &self,
+        msg: M,
+    ) -> Result<ResponseHandle<<A as UnsendHandler<M>>::Output>, ChannelSendError<Envelope<A>>>
+    where
+        M: Message + 'static,
+        A: UnsendHandler<M>,
+    {
+        let (tx, rx) = crate::response::channel();
+        let envelope = envelope::pack_local(msg, Some(tx));
+        if !self.intake.try_enter() {
+            return Err(ChannelSendError::RateLimited(envelope));
+        }
+        self.sender
+            .send(envelope)
+            .await
+            .map_err::<ChannelSendError<Envelope<A>>, _>(Into::into)?;
+        Ok(ResponseHandle(rx.into()))
+    }
+
+    pub fn try_send<M>(
+        &self,
+        msg: M,
+    ) -> Result<ResponseHandle<<A as UnsendHandler<M>>::Output>, ChannelTrySendError<Envelope<A>>>
+    where
+        M: Message + 'static,
+        A: UnsendHandler<M>,
+    {
+        let (tx, rx) = crate::response::channel();
+        let envelope = envelope::pack_local(msg, Some(tx));
+        if !self.intake.try_enter() {
+            return Err(ChannelTrySendError::RateLimited(envelope));
+        }
+        self.sender
+            .try_send(envelope)
+            .map_err::<ChannelTrySendError<Envelope<A>>, _>(Into::into)?;
+        Ok(ResponseHandle(rx.into()))
+    }
+
+    /// 发送不需要响应的消息
+    pub async fn do_send<M>(&self, msg: M) -> Result<(), ChannelSendError<Envelope<A>>>
+    where
+        M: Message + 'static,
+        A: UnsendHandler<M>,
+    {
+        let envelope = envelope::pack_local(msg, None);
+        if !self.intake.try_enter() {
+            return Err(ChannelSendError::RateLimited(envelope));
+        }
+        self.sender.send(envelope).await.map_err(Into::into)
+    }
+
+    /// send + recv
+    pub async fn call<M>(&self, msg: M) -> Result<<A as UnsendHandler<M>>::Output, CallError<A>>
+    where
+        M: Message + 'static,
+        A: UnsendHandler<M>,
+    {
+        Ok(self.send(msg).await?.recv().await?)
+    }
+}
+
+impl<A> Clone for UnsendAddress<A>
+where
+    A: UnsendActor,
+{
+    fn clone(&self) -> Self {
+        UnsendAddress(self.0.clone())
+    }
+}
+
+impl<A> Deref for UnsendAddress<A>
+where
+    A: UnsendActor,
+{
+    type Target = LocalAddress<A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<A> Context<A>
+where
+    A: UnsendActor,
+{
+    /// 自身的[`UnsendAddress`], 所有地址都已经被丢弃时返回`None`
+    #[inline]
+    pub fn unsend_address(&self) -> Option<UnsendAddress<A>> {
+        self.self_addr
+            .upgrade()
+            .map(|addr| UnsendAddress((*addr).clone()))
+    }
+}
+
+/// 在当前线程的`LocalSet`上生成[`UnsendActor`]
+///
+/// `UnsendBroker`本身不能跨线程, 需要跨线程时发送[`UnsendBroker::addr`]的clone.
+pub struct UnsendBroker<A>
+where
+    A: UnsendActor,
+{
+    /// 和[`Context`]中的`self_addr`对应, 决定信箱是否关闭
+    _local: Arc<LocalAddress<A>>,
+    addr: UnsendAddress<A>,
+    actor_runner_handles: Vec<SpawnHandle<A>>,
+}
+
+impl<A> UnsendBroker<A>
+where
+    A: UnsendActor,
+{
+    #[inline]
+    pub fn spawn(quantity: usize) -> Self
+    where
+        A::Args: Default,
+    {
+        UnsendBroker::spawn_with_args(quantity, Default::default())
+    }
+
+    /// 必须在`LocalSet`中调用, 见[`tokio::task::spawn_local`]
+    ///
+    /// [`UnsendActor::create`]也在生成的任务中执行.
+    pub fn spawn_with_args(quantity: usize, args: A::Args) -> Self {
+        let (local, global_context) = new_global_context(args, Placement::default());
+
+        let actor_runner_handles = (0..quantity)
+            .map(|_| {
+                let mut context = Context::new(global_context.clone());
+                let status = Arc::clone(&context.status);
+                let join_handle = tokio::task::spawn_local(async move {
+                    let actor = A::create(&mut context).await;
+                    ActorRunner::local(actor, context).run().await
+                });
+                SpawnHandle::from_local(join_handle, status)
+            })
+            .collect();
+
+        UnsendBroker {
+            addr: UnsendAddress((*local).clone()),
+            _local: local,
+            actor_runner_handles,
+        }
+    }
+
+    #[inline]
+    pub fn addr(&self) -> &UnsendAddress<A> {
+        &self.addr
+    }
+
+    /// 等待所有actor结束, 按照生成的顺序返回每个actor的[`ActorExit`]
+    ///
+    /// 如果被[`LocalAddress::pause`]暂停, 会先恢复.
+    pub async fn wait_for_actors(self) -> Vec<ActorExit> {
+        let UnsendBroker {
+            _local,
+            addr,
+            actor_runner_handles,
+        } = self;
+        addr.resume();
+        drop((_local, addr));
+        join_all(actor_runner_handles.into_iter().map(SpawnHandle::join)).await
+    }
+
+    pub fn abort(&self) {
+        for handle in &self.actor_runner_handles {
+            handle.abort();
+        }
+    }
+}
+
+impl<A> Deref for UnsendBroker<A>
+where
+    A: UnsendActor,
+{
+    type Target = UnsendAddress<A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use tokio::task::LocalSet;
+
+    use super::{UnsendActor, UnsendBroker, UnsendHandler};
+    use crate::Context;
+
+    /// 持有`Rc`, 不是`Send`的
+    struct Counter {
+        total: Rc<RefCell<u32>>,
+    }
+
+    struct Add(u32);
+    struct Boom;
+
+    impl UnsendActor for Counter {
+        const MAIL_BOX_SIZE: u32 = 8;
+        type Args = ();
+
+        async fn create(_ctx: &mut Context<Self>) -> Self {
+            Counter {
+                total: Default::default(),
+            }
+        }
+    }
+
+    impl UnsendHandler<Add> for Counter {
+        type Output = u32;
+
+        async fn handle(&mut self, Add(n): Add, _ctx: &mut Context<Self>) -> u32 {
+            tokio::task::yield_now().await;
+            *self.total.borrow_mut() += n;
+            *self.total.borrow()
+        }
+    }
+
+    impl UnsendHandler<Boom> for Counter {
+        type Output = ();
+
+        async fn handle(&mut self, _: Boom, _ctx: &mut Context<Self>) {
+            panic!("expected")
+        }
+    }
+
+    #[tokio::test]
+    async fn call_from_other_task_and_restart() {
+        LocalSet::new()
+            .run_until(async {
+                let broker = UnsendBroker::<Counter>::spawn(1);
+                let addr = broker.addr().clone();
+                let total = tokio::spawn(async move {
+                    addr.call(Add(2)).await.unwrap();
+                    addr.call(Add(3)).await.unwrap()
+                })
+                .await
+                .unwrap();
+                assert_eq!(total, 5);
+
+                // panic之后重置, `create`重新创建
+                assert!(broker.call(Boom).await.is_err());
+                assert_eq!(broker.call(Add(1)).await.unwrap(), 1);
+
+                let exits = broker.wait_for_actors().await;
+                assert!(exits[0].is_clean());
+                assert_eq!(exits[0].panic_restarts, 1);
+            })
+            .await;
+    }
+}